@@ -0,0 +1,223 @@
+//! Golden wire-format vectors for cross-implementation conformance.
+//!
+//! Every vector in `vectors()` is one frame exactly as the server's codecs write it, committed
+//! under `vectors/` as `<name>.hex`: lowercase hex of the command byte, the big-endian u32
+//! payload length, then the protobuf payload. Clients in other languages can decode each file
+//! and compare it against the fields in `src/vectors.rs`, and encode those fields to reproduce
+//! the file byte for byte.
+//!
+//! `cargo run -p conformance -- emit` rewrites the fixtures after an intended wire change;
+//! `verify`, which `cargo test` also runs, fails on any unintended one.
+
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bytes::BytesMut;
+use server::parser::{ClientCodec, ServerCodec};
+use thiserror::Error;
+use tokio_util::codec::Decoder;
+
+mod vectors;
+
+pub use vectors::{Expected, Vector, vectors};
+
+/// Directory holding the committed fixtures.
+pub const VECTORS_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/vectors");
+const FIXTURE_EXTENSION: &str = "hex";
+const HEX_RADIX: u32 = 16;
+
+#[derive(Debug, Error)]
+pub enum ConformanceError {
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("fixture {name} is not valid hex")]
+    InvalidHex { name: &'static str },
+    #[error("fixture {name} differs from the current encoding")]
+    Mismatch { name: &'static str },
+    #[error("fixture {name} does not decode: {source}")]
+    Undecodable {
+        name: &'static str,
+        #[source]
+        source: Box<dyn Error + Send + Sync>,
+    },
+    #[error("fixture {name} has {length} bytes left over after decoding")]
+    TrailingBytes { name: &'static str, length: usize },
+    #[error("fixture {name} decodes to different frames than its vector")]
+    WrongFrames { name: &'static str },
+    #[error("{path} has no matching vector")]
+    Orphaned { path: PathBuf },
+}
+
+/// Writes every vector to `directory`, replacing existing fixtures.
+pub fn emit(directory: &Path) -> Result<(), ConformanceError> {
+    fs::create_dir_all(directory)
+        .map_err(|source| ConformanceError::Io { path: directory.to_path_buf(), source })?;
+    for vector in vectors() {
+        let path = fixture_path(directory, vector.name);
+        fs::write(&path, to_hex(&vector.frame) + "\n")
+            .map_err(|source| ConformanceError::Io { path, source })?;
+    }
+    Ok(())
+}
+
+/// Checks that every vector's fixture in `directory` matches the current encoding, decodes
+/// back to the vector's frames, and that no fixture is left without a vector.
+pub fn verify(directory: &Path) -> Result<(), ConformanceError> {
+    let vectors = vectors();
+    for vector in &vectors {
+        verify_vector(directory, vector)?;
+    }
+
+    let names: HashSet<&str> = vectors.iter().map(|vector| vector.name).collect();
+    let entries = fs::read_dir(directory)
+        .map_err(|source| ConformanceError::Io { path: directory.to_path_buf(), source })?;
+    for entry in entries {
+        let path = entry
+            .map_err(|source| ConformanceError::Io { path: directory.to_path_buf(), source })?
+            .path();
+        let is_fixture = path.extension().is_some_and(|extension| extension == FIXTURE_EXTENSION);
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        if is_fixture && !names.contains(stem) {
+            return Err(ConformanceError::Orphaned { path });
+        }
+    }
+    Ok(())
+}
+
+fn verify_vector(directory: &Path, vector: &Vector) -> Result<(), ConformanceError> {
+    let path = fixture_path(directory, vector.name);
+    let text = fs::read_to_string(&path).map_err(|source| ConformanceError::Io { path, source })?;
+    let fixture =
+        from_hex(text.trim()).ok_or(ConformanceError::InvalidHex { name: vector.name })?;
+    if fixture != vector.frame {
+        return Err(ConformanceError::Mismatch { name: vector.name });
+    }
+
+    let decoded = match &vector.expected {
+        Expected::ToServer(_) => {
+            Expected::ToServer(decode_all(ServerCodec::default(), vector.name, &fixture)?)
+        }
+        Expected::ToClient(_) => {
+            Expected::ToClient(decode_all(ClientCodec::default(), vector.name, &fixture)?)
+        }
+    };
+    if decoded != vector.expected {
+        return Err(ConformanceError::WrongFrames { name: vector.name });
+    }
+    Ok(())
+}
+
+/// Decodes every frame in `bytes`; a batch command yields several.
+fn decode_all<D>(
+    mut codec: D,
+    name: &'static str,
+    bytes: &[u8],
+) -> Result<Vec<D::Item>, ConformanceError>
+where
+    D: Decoder,
+    D::Error: Error + Send + Sync + 'static,
+{
+    let mut buffer = BytesMut::from(bytes);
+    let mut frames = Vec::new();
+    while let Some(frame) = codec
+        .decode(&mut buffer)
+        .map_err(|source| ConformanceError::Undecodable { name, source: Box::new(source) })?
+    {
+        frames.push(frame);
+    }
+    if !buffer.is_empty() {
+        return Err(ConformanceError::TrailingBytes { name, length: buffer.len() });
+    }
+    Ok(frames)
+}
+
+fn fixture_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(name).with_extension(FIXTURE_EXTENSION)
+}
+
+/// Lowercase hex with no separators.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut text, byte| {
+        let _ = write!(text, "{byte:02x}");
+        text
+    })
+}
+
+/// Parses hex written by `to_hex`; either case is accepted.
+pub fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let high = char::from(pair[0]).to_digit(HEX_RADIX)?;
+            let low = char::from(pair[1]).to_digit(HEX_RADIX)?;
+            u8::try_from(high * HEX_RADIX + low).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use server::parser::Command;
+
+    use super::*;
+
+    const ALL_COMMANDS: [Command; 16] = [
+        Command::Info,
+        Command::Connect,
+        Command::Publish,
+        Command::Subscribe,
+        Command::UnSubscribe,
+        Command::Message,
+        Command::Ack,
+        Command::Error,
+        Command::Ping,
+        Command::Pong,
+        Command::Disconnect,
+        Command::SubAck,
+        Command::PublishBatch,
+        Command::Flow,
+        Command::Extension,
+        Command::MessageAck,
+    ];
+
+    #[test]
+    fn committed_fixtures_match_current_encoding() {
+        verify(Path::new(VECTORS_DIRECTORY)).unwrap();
+    }
+
+    #[test]
+    fn every_command_has_a_vector() {
+        let covered: HashSet<u8> = vectors().iter().map(|vector| vector.frame[0]).collect();
+
+        let missing: Vec<Command> = ALL_COMMANDS
+            .into_iter()
+            .filter(|command| !covered.contains(&(*command as u8)))
+            .collect();
+
+        assert!(missing.is_empty(), "no vector for {missing:?}");
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = [0x00, 0x0c, 0xab, 0xff];
+
+        assert_eq!(from_hex(&to_hex(&bytes)), Some(bytes.to_vec()));
+    }
+
+    #[test]
+    fn odd_length_hex_is_rejected() {
+        assert_eq!(from_hex("abc"), None);
+    }
+}
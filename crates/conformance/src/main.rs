@@ -0,0 +1,28 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use conformance::VECTORS_DIRECTORY;
+
+const USAGE: &str = "usage: conformance <emit|verify> [directory]";
+
+fn main() -> ExitCode {
+    let mut arguments = std::env::args().skip(1);
+    let command = arguments.next();
+    let directory =
+        arguments.next().map_or_else(|| PathBuf::from(VECTORS_DIRECTORY), PathBuf::from);
+
+    let result = match command.as_deref() {
+        Some("emit") => conformance::emit(&directory),
+        Some("verify") => conformance::verify(&directory),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use bytes::{Bytes, BytesMut};
+use server::{
+    error::ErrorCode,
+    headers::MAX_HEADER_BLOCK_BYTES,
+    parser::{ClientFrame, CommandCodec, Frame, PROTOCOL_VERSION, encode_frame, pb},
+    topic::{MAX_LAYERS, MAX_TOPIC_LENGTH},
+};
+
+const TOPIC: &[u8] = b"sensors/temperature";
+const REPLY_TOPIC: &[u8] = b"_INBOX/7f3a";
+const PAYLOAD: &[u8] = b"23.5";
+/// A `Headers` block holding `content-type: text/plain`: entry count, then each key and value
+/// behind a big-endian u16 length.
+const HEADER: &[u8] = b"\x01\x00\x0ccontent-type\x00\x0atext/plain";
+const PUBLISH_ID: u64 = 42;
+const SUBSCRIPTION_ID: u32 = 7;
+const TOPIC_ALIAS: u32 = 1;
+const MAX_MSGS: u64 = 10;
+const MAX_PAYLOAD: u32 = 64 * 1024;
+const KEEPALIVE_SECS: u32 = 30;
+const SEQUENCE: u64 = 1001;
+const PUBLIC_KEY_BYTES: usize = 32;
+const SIGNATURE_BYTES: usize = 64;
+const NONCE_BYTES: usize = 16;
+const CLIENT_ID: u64 = 99;
+const TOPIC_ALIAS_MAXIMUM: u32 = 64;
+const MAX_REASSEMBLED_PAYLOAD: u32 = 16 * 1024 * 1024;
+const MAX_SUBSCRIPTIONS: u32 = 1000;
+const FLOW_CREDITS: u32 = 16;
+const PRIORITY: u32 = 7;
+const RECEIVED_AT_MS: u64 = 1_700_000_000_000;
+const DELIVERY_ID: u64 = 12;
+/// Above 127, so the sub-command id takes a multi-byte varint.
+const EXTENSION_COMMAND: u64 = 300;
+
+/// The frames a vector decodes to, on the side that receives it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    ToServer(Vec<Frame>),
+    ToClient(Vec<ClientFrame>),
+}
+
+/// One golden frame: its fixture name, encoded bytes, and decoded form.
+#[derive(Debug, Clone)]
+pub struct Vector {
+    pub name: &'static str,
+    pub frame: Bytes,
+    pub expected: Expected,
+}
+
+fn encode<T: CommandCodec>(item: &T) -> Bytes {
+    let mut frame = BytesMut::with_capacity(item.frame_len());
+    // Only a payload over u32::MAX bytes can fail, and every vector is far below that.
+    encode_frame(item, &mut frame).expect("vector encodes");
+    frame.freeze()
+}
+
+fn to_server<T: CommandCodec>(name: &'static str, item: T, wrap: fn(T) -> Frame) -> Vector {
+    Vector { name, frame: encode(&item), expected: Expected::ToServer(vec![wrap(item)]) }
+}
+
+fn to_client<T: CommandCodec>(name: &'static str, item: T, wrap: fn(T) -> ClientFrame) -> Vector {
+    Vector { name, frame: encode(&item), expected: Expected::ToClient(vec![wrap(item)]) }
+}
+
+/// A batch decodes to one Publish frame per entry, and an empty batch to none.
+fn publish_batch(name: &'static str, publishes: Vec<pb::Publish>) -> Vector {
+    let batch = pb::PublishBatch { publishes };
+    let frame = encode(&batch);
+    Vector {
+        name,
+        frame,
+        expected: Expected::ToServer(batch.publishes.into_iter().map(Frame::Publish).collect()),
+    }
+}
+
+fn publish() -> pb::Publish {
+    pb::Publish {
+        topic: Bytes::from_static(TOPIC),
+        payload: Bytes::from_static(PAYLOAD),
+        ..Default::default()
+    }
+}
+
+fn message() -> pb::Message {
+    pb::Message {
+        topic: Bytes::from_static(TOPIC),
+        subscription_id: SUBSCRIPTION_ID,
+        payload: Bytes::from_static(PAYLOAD),
+        ..Default::default()
+    }
+}
+
+fn extension() -> pb::Extension {
+    pb::Extension { command: EXTENSION_COMMAND, payload: Bytes::from_static(PAYLOAD) }
+}
+
+fn connect(auth_method: pb::AuthMethod, credentials: pb::connect::Credentials) -> pb::Connect {
+    pb::Connect {
+        version: PROTOCOL_VERSION,
+        auth_method: auth_method as i32,
+        credentials: Some(credentials),
+        ..Default::default()
+    }
+}
+
+/// Every golden vector, covering each command and the optional fields and flags it carries.
+pub fn vectors() -> Vec<Vector> {
+    vec![
+        to_server(
+            "connect_no_auth",
+            pb::Connect { version: PROTOCOL_VERSION, ..Default::default() },
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_verbose_with_limits",
+            pb::Connect {
+                version: PROTOCOL_VERSION,
+                verbose: true,
+                max_payload: Some(MAX_PAYLOAD),
+                keepalive_secs: Some(KEEPALIVE_SECS),
+                client_name: Some("sensor-7".to_string()),
+                ..Default::default()
+            },
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_receive_timestamps",
+            pb::Connect {
+                version: PROTOCOL_VERSION,
+                receive_timestamps: true,
+                ..Default::default()
+            },
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_no_echo",
+            pb::Connect { version: PROTOCOL_VERSION, no_echo: true, ..Default::default() },
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_password",
+            connect(
+                pb::AuthMethod::Password,
+                pb::connect::Credentials::PasswordAuth(pb::PasswordAuth {
+                    username: "alice".to_string(),
+                    password: "correct horse".to_string(),
+                }),
+            ),
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_challenge",
+            connect(
+                pb::AuthMethod::Challenge,
+                pb::connect::Credentials::ChallengeAuth(pb::ChallengeAuth {
+                    public_key: vec![0x11; PUBLIC_KEY_BYTES],
+                    signature: vec![0x22; SIGNATURE_BYTES],
+                }),
+            ),
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_bearer_token",
+            connect(
+                pb::AuthMethod::Token,
+                pb::connect::Credentials::BearerToken(pb::BearerToken {
+                    token: b"token-123".to_vec(),
+                }),
+            ),
+            Frame::Connect,
+        ),
+        to_server(
+            "connect_with_will",
+            pb::Connect {
+                version: PROTOCOL_VERSION,
+                will: Some(pb::Will {
+                    topic: b"sensors/status".to_vec(),
+                    payload: b"offline".to_vec(),
+                    header: HEADER.to_vec(),
+                    retain: true,
+                }),
+                ..Default::default()
+            },
+            Frame::Connect,
+        ),
+        to_server("publish_minimal", publish(), Frame::Publish),
+        to_server(
+            "publish_with_header",
+            pb::Publish { header: Bytes::from_static(HEADER), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_with_reply_to",
+            pb::Publish { reply_to: Some(Bytes::from_static(REPLY_TOPIC)), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_acknowledged",
+            pb::Publish { publish_id: PUBLISH_ID, ..publish() },
+            Frame::Publish,
+        ),
+        to_server("publish_retained", pb::Publish { retain: true, ..publish() }, Frame::Publish),
+        to_server(
+            "publish_binding_topic_alias",
+            pb::Publish { topic_alias: Some(TOPIC_ALIAS), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_topic_alias_only",
+            pb::Publish { topic: Bytes::new(), topic_alias: Some(TOPIC_ALIAS), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_first_fragment",
+            pb::Publish { fragment: Some(pb::Fragment { index: 0, last: false }), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_last_fragment",
+            pb::Publish { fragment: Some(pb::Fragment { index: 1, last: true }), ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_at_least_once",
+            pb::Publish { qos: pb::QosLevel::AtLeastOnce as i32, ..publish() },
+            Frame::Publish,
+        ),
+        to_server(
+            "publish_with_priority",
+            pb::Publish { priority: Some(PRIORITY), ..publish() },
+            Frame::Publish,
+        ),
+        publish_batch(
+            "publish_batch",
+            vec![publish(), pb::Publish { publish_id: PUBLISH_ID, ..publish() }],
+        ),
+        publish_batch("publish_batch_empty", Vec::new()),
+        to_server(
+            "subscribe_minimal",
+            pb::Subscribe {
+                topic: TOPIC.to_vec(),
+                subscription_id: SUBSCRIPTION_ID,
+                ..Default::default()
+            },
+            Frame::Subscribe,
+        ),
+        to_server(
+            "subscribe_wildcard",
+            pb::Subscribe {
+                topic: b"sensors/+/temperature".to_vec(),
+                subscription_id: SUBSCRIPTION_ID,
+                ..Default::default()
+            },
+            Frame::Subscribe,
+        ),
+        to_server(
+            "subscribe_queue_group",
+            pb::Subscribe {
+                topic: TOPIC.to_vec(),
+                subscription_id: SUBSCRIPTION_ID,
+                queue_group: Some("workers".to_string()),
+                ..Default::default()
+            },
+            Frame::Subscribe,
+        ),
+        to_server(
+            "subscribe_at_least_once",
+            pb::Subscribe {
+                topic: TOPIC.to_vec(),
+                subscription_id: SUBSCRIPTION_ID,
+                qos: pb::QosLevel::AtLeastOnce as i32,
+                ..Default::default()
+            },
+            Frame::Subscribe,
+        ),
+        to_server(
+            "subscribe_max_msgs",
+            pb::Subscribe {
+                topic: TOPIC.to_vec(),
+                subscription_id: SUBSCRIPTION_ID,
+                max_msgs: Some(MAX_MSGS),
+                ..Default::default()
+            },
+            Frame::Subscribe,
+        ),
+        to_server(
+            "unsubscribe",
+            pb::UnSubscribe { subscription_id: SUBSCRIPTION_ID, max_msgs: None },
+            Frame::UnSubscribe,
+        ),
+        to_server(
+            "unsubscribe_after_max_msgs",
+            pb::UnSubscribe { subscription_id: SUBSCRIPTION_ID, max_msgs: Some(MAX_MSGS) },
+            Frame::UnSubscribe,
+        ),
+        to_server(
+            "flow",
+            pb::Flow { subscription_id: SUBSCRIPTION_ID, credits: FLOW_CREDITS },
+            Frame::Flow,
+        ),
+        to_server("message_ack", pb::MessageAck { delivery_id: DELIVERY_ID }, Frame::MessageAck),
+        to_server("extension_to_server", extension(), Frame::Extension),
+        to_server("ping", pb::Ping {}, Frame::Ping),
+        to_server("disconnect_from_client", pb::Disconnect::default(), Frame::Disconnect),
+        to_client(
+            "info_minimal",
+            pb::Info {
+                version: PROTOCOL_VERSION,
+                server_id: "server-1".to_string(),
+                ..Default::default()
+            },
+            ClientFrame::Info,
+        ),
+        to_client(
+            "info_full",
+            pb::Info {
+                version: PROTOCOL_VERSION,
+                server_id: "server-1".to_string(),
+                server_name: "ocypode".to_string(),
+                max_payload: MAX_PAYLOAD,
+                client_id: CLIENT_ID,
+                requires_auth: true,
+                tls_verify: true,
+                max_keepalive_secs: KEEPALIVE_SECS,
+                nonce: vec![0x33; NONCE_BYTES],
+                topic_alias_maximum: TOPIC_ALIAS_MAXIMUM,
+                max_reassembled_payload: MAX_REASSEMBLED_PAYLOAD,
+                connect_urls: vec!["quic://a.example:4433".to_string()],
+                max_topic_length: Some(MAX_TOPIC_LENGTH as u32),
+                max_topic_layers: Some(MAX_LAYERS as u32),
+                max_header_bytes: Some(MAX_HEADER_BLOCK_BYTES as u32),
+                max_subscriptions: Some(MAX_SUBSCRIPTIONS),
+            },
+            ClientFrame::Info,
+        ),
+        to_client("message_minimal", message(), ClientFrame::Message),
+        to_client(
+            "message_full",
+            pb::Message {
+                header: Bytes::from_static(HEADER),
+                reply_to: Some(Bytes::from_static(REPLY_TOPIC)),
+                sequence: Some(SEQUENCE),
+                retained: true,
+                ..message()
+            },
+            ClientFrame::Message,
+        ),
+        to_client(
+            "message_fragment",
+            pb::Message { fragment: Some(pb::Fragment { index: 0, last: false }), ..message() },
+            ClientFrame::Message,
+        ),
+        to_client(
+            "message_with_priority",
+            pb::Message { priority: Some(PRIORITY), ..message() },
+            ClientFrame::Message,
+        ),
+        to_client(
+            "message_with_receive_timestamp",
+            pb::Message { received_at_ms: Some(RECEIVED_AT_MS), ..message() },
+            ClientFrame::Message,
+        ),
+        to_client(
+            "message_at_least_once",
+            pb::Message { delivery_id: Some(DELIVERY_ID), ..message() },
+            ClientFrame::Message,
+        ),
+        to_client("extension_to_client", extension(), ClientFrame::Extension),
+        to_client("ack", pb::Ack { publish_id: PUBLISH_ID }, ClientFrame::Ack),
+        to_client(
+            "error_recoverable",
+            pb::Error {
+                publish_id: PUBLISH_ID,
+                code: ErrorCode::PermissionViolation as u32,
+                reason: None,
+                fatal: false,
+            },
+            ClientFrame::Error,
+        ),
+        to_client(
+            "error_fatal_with_reason",
+            pb::Error {
+                publish_id: 0,
+                code: ErrorCode::AuthFailed as u32,
+                reason: Some("bad credentials".to_string()),
+                fatal: true,
+            },
+            ClientFrame::Error,
+        ),
+        to_client("pong", pb::Pong {}, ClientFrame::Pong),
+        to_client(
+            "suback_granted",
+            pb::SubAck { subscription_id: SUBSCRIPTION_ID, granted: true, code: None },
+            ClientFrame::SubAck,
+        ),
+        to_client(
+            "suback_denied",
+            pb::SubAck {
+                subscription_id: SUBSCRIPTION_ID,
+                granted: false,
+                code: Some(ErrorCode::PermissionViolation as u32),
+            },
+            ClientFrame::SubAck,
+        ),
+        to_client(
+            "disconnect_server_shutdown",
+            pb::Disconnect {
+                reason: pb::DisconnectReason::ServerShutdown as i32,
+                text: Some("restarting".to_string()),
+            },
+            ClientFrame::Disconnect,
+        ),
+    ]
+}
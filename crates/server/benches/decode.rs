@@ -0,0 +1,40 @@
+//! Publish decode cost for a 1 MiB payload: the zero-copy path the codecs use, where the
+//! payload is a slice of the frame buffer, against decoding from a borrowed slice, which
+//! copies the payload out. Run with `cargo bench -p server --bench decode`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use prost::Message;
+use server::parser::{CommandCodec, pb};
+
+const PAYLOAD_BYTES: usize = 1024 * 1024;
+const ITERATIONS: u32 = 1_000;
+
+fn time_per_decode(mut decode: impl FnMut() -> pb::Publish) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(decode());
+    }
+    started.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let publish = pb::Publish {
+        topic: Bytes::from_static(b"bench/decode"),
+        payload: Bytes::from(vec![0xAB; PAYLOAD_BYTES]),
+        ..Default::default()
+    };
+    let encoded = Bytes::from(publish.encode_to_vec());
+
+    let zero_copy =
+        time_per_decode(|| pb::Publish::decode_payload(encoded.clone()).expect("valid frame"));
+    let copying = time_per_decode(|| pb::Publish::decode(&encoded[..]).expect("valid frame"));
+
+    println!("publish decode, {PAYLOAD_BYTES} byte payload, {ITERATIONS} iterations");
+    println!("  zero-copy (Bytes):   {zero_copy:?} per decode");
+    println!("  copying   (&[u8]):   {copying:?} per decode");
+}
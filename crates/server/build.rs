@@ -8,6 +8,22 @@ fn main() -> std::io::Result<()> {
 
     tonic_prost_build::configure()
         .build_server(false)
+        // Debug for these is written by hand in src/redact.rs so logs never carry payloads
+        // or credentials.
+        .skip_debug([
+            ".ocypode.pubsub.v1.Publish",
+            ".ocypode.pubsub.v1.Message",
+            ".ocypode.pubsub.v1.Will",
+            ".ocypode.pubsub.v1.PasswordAuth",
+            ".ocypode.pubsub.v1.BearerToken",
+            ".ocypode.pubsub.v1.ChallengeAuth",
+            ".ocypode.pubsub.v1.Extension",
+        ])
+        // Decoding these from a `Bytes` frame slices topic, payload and headers out of the
+        // read buffer instead of copying them.
+        .bytes(".ocypode.pubsub.v1.Publish")
+        .bytes(".ocypode.pubsub.v1.Message")
+        .bytes(".ocypode.pubsub.v1.Extension")
         .compile_protos(&[proto_file], &[proto_root])?;
 
     Ok(())
@@ -2,8 +2,18 @@
 //       Future work: PasswordAuthenticator, JWT-based auth, etc.
 //       Authorization (subject-level permissions) is handled separately in permission.rs.
 
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
 use crate::parser::pb;
 
+/// Size of the per-connection nonce advertised in INFO.
+pub const NONCE_BYTES: usize = 16;
+/// The operating system's cryptographically secure random device.
+const RANDOM_DEVICE: &str = "/dev/urandom";
+
 #[allow(dead_code)]
 pub enum AuthOutcome {
     Accepted,
@@ -11,15 +21,97 @@ pub enum AuthOutcome {
 }
 
 /// Validates credentials presented in the CONNECT message.
+/// `nonce` is the value advertised to this connection in INFO.
 pub trait Authenticator: Send + Sync + 'static {
-    fn authenticate(&self, connect: &pb::Connect) -> AuthOutcome;
+    fn authenticate(&self, connect: &pb::Connect, nonce: &[u8]) -> AuthOutcome;
 }
 
 /// Accepts all connections without credential verification.
 pub struct NoAuthAuthenticator;
 
 impl Authenticator for NoAuthAuthenticator {
-    fn authenticate(&self, _connect: &pb::Connect) -> AuthOutcome {
+    fn authenticate(&self, _connect: &pb::Connect, _nonce: &[u8]) -> AuthOutcome {
         AuthOutcome::Accepted
     }
 }
+
+/// Checks that `signature` over `nonce` was made by the private key matching `public_key`,
+/// e.g. with ed25519. Supplied by the deployment so the server stays crypto-library agnostic.
+pub trait SignatureVerifier: Send + Sync + 'static {
+    fn verify(&self, public_key: &[u8], nonce: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Accepts only CHALLENGE credentials whose signature over the connection nonce verifies.
+pub struct ChallengeAuthenticator<V> {
+    verifier: V,
+}
+
+impl<V: SignatureVerifier> ChallengeAuthenticator<V> {
+    #[allow(dead_code)]
+    pub fn new(verifier: V) -> Self {
+        Self { verifier }
+    }
+}
+
+impl<V: SignatureVerifier> Authenticator for ChallengeAuthenticator<V> {
+    fn authenticate(&self, connect: &pb::Connect, nonce: &[u8]) -> AuthOutcome {
+        let Some(pb::connect::Credentials::ChallengeAuth(challenge)) = &connect.credentials else {
+            return AuthOutcome::Rejected { reason: "challenge credentials required".to_string() };
+        };
+        if self.verifier.verify(&challenge.public_key, nonce, &challenge.signature) {
+            AuthOutcome::Accepted
+        } else {
+            AuthOutcome::Rejected { reason: "signature verification failed".to_string() }
+        }
+    }
+}
+
+/// Fills `destination` from the operating system's CSPRNG.
+pub(crate) fn fill_random(destination: &mut [u8]) -> io::Result<()> {
+    File::open(RANDOM_DEVICE)?.read_exact(destination)
+}
+
+/// Fresh, unpredictable nonce for one connection, taken from `fill_random`.
+pub fn generate_nonce() -> io::Result<[u8; NONCE_BYTES]> {
+    let mut nonce = [0; NONCE_BYTES];
+    fill_random(&mut nonce)?;
+    Ok(nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ClientOutbound;
+
+    /// Accepts a signature equal to the public key followed by the nonce.
+    struct ConcatenationVerifier;
+
+    impl SignatureVerifier for ConcatenationVerifier {
+        fn verify(&self, public_key: &[u8], nonce: &[u8], signature: &[u8]) -> bool {
+            signature == [public_key, nonce].concat()
+        }
+    }
+
+    #[test]
+    fn challenge_authenticator_accepts_valid_signature() {
+        let nonce = generate_nonce().unwrap();
+        let signature = [b"key".as_slice(), &nonce].concat();
+        let connect = ClientOutbound::connect_with_challenge(1, false, b"key".to_vec(), signature);
+
+        let outcome =
+            ChallengeAuthenticator::new(ConcatenationVerifier).authenticate(&connect, &nonce);
+
+        assert!(matches!(outcome, AuthOutcome::Accepted));
+    }
+
+    #[test]
+    fn challenge_authenticator_rejects_signature_over_other_nonce() {
+        let signature = [b"key".as_slice(), &generate_nonce().unwrap()].concat();
+        let connect = ClientOutbound::connect_with_challenge(1, false, b"key".to_vec(), signature);
+
+        let outcome = ChallengeAuthenticator::new(ConcatenationVerifier)
+            .authenticate(&connect, &generate_nonce().unwrap());
+
+        assert!(matches!(outcome, AuthOutcome::Rejected { .. }));
+    }
+}
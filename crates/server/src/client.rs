@@ -2,39 +2,103 @@
 //       FramedRead → Handshake → Frame dispatch → Permission check → Router → FramedWrite.
 //       Permission check (permission.rs) and routing (router.rs) are stubs pending implementation.
 
-use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
+use bytes::BytesMut;
 use futures_util::SinkExt;
 use thiserror::Error;
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     sync::mpsc,
+    time::MissedTickBehavior,
 };
 use tokio_stream::StreamExt;
 use tokio_util::codec::{FramedRead, FramedWrite};
 
 static CLIENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+/// Reason sent with the ERROR answering a Publish that an interceptor dropped.
+const PUBLISH_DROPPED_REASON: &str = "publish dropped by interceptor";
 
+#[cfg(feature = "trace")]
+use crate::trace::{FrameTracer, TraceDirection};
 use crate::{
-    auth::Authenticator,
+    auth::{Authenticator, generate_nonce},
+    clock::{Clock, SystemClock},
     config::ServerConfig,
-    error::ServerCodecError,
+    dedup::DedupWindow,
+    delivery::{Admission, DeliveryControls, InflightTracker, PausedDeliveries, effective_qos},
+    error::{CodecError, ErrorCode, FragmentError, ServerCodecError, TopicError},
+    fragment::Reassembler,
     handshake::{CompletedHandshake, HandshakeError, PendingHandshake},
-    parser::{Frame, OutboundMessage, PROTOCOL_VERSION, ServerCodec, ServerOutbound, pb},
+    interceptor::{Action, InterceptorChain},
+    parser::{
+        BudgetedDecoder, CommandCodec, Frame, MAXIMUM_PAYLOAD_BYTES, OutboundMessage,
+        PROTOCOL_VERSION, ReadBudget, ServerCodec, ServerOutbound, pb,
+    },
+    topic::{Topic, TopicFilter},
+    topic_alias::TopicAliasTable,
     transport::Transport,
 };
 
+/// Every failure a client session can hit, so the dispatch loop works with a single `Result`.
 #[derive(Debug, Error)]
 pub enum ClientError {
     #[error(transparent)]
     Handshake(#[from] HandshakeError),
     #[error(transparent)]
     Codec(#[from] ServerCodecError),
+    #[error(transparent)]
+    Topic(#[from] TopicError),
     #[error("outbound channel closed")]
     OutboundChannelClosed,
+    #[error("no frame received within {timeout:?}")]
+    IdleTimeout { timeout: Duration },
+}
+
+impl ClientError {
+    /// Maps the failure to the application error code reported to the client on close.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            ClientError::Handshake(error) => error.error_code(),
+            ClientError::Codec(ServerCodecError::Codec(CodecError::BufferExhausted { .. })) => {
+                ErrorCode::BufferExhausted
+            }
+            ClientError::Codec(ServerCodecError::Codec(
+                CodecError::PayloadTooLarge { .. } | CodecError::FrameTooLarge { .. },
+            )) => ErrorCode::PayloadTooLarge,
+            // Encoding only ever sees frames the server built, so a failure is a server bug.
+            ClientError::Codec(ServerCodecError::Codec(CodecError::Encode(_))) => {
+                ErrorCode::InternalError
+            }
+            // The code is not fatal on its own, but the byte stream cannot be resynchronized
+            // after an undecodable frame, so the connection closes regardless.
+            ClientError::Codec(ServerCodecError::Codec(_)) => ErrorCode::ProtocolViolation,
+            ClientError::Codec(ServerCodecError::Io(_)) | ClientError::OutboundChannelClosed => {
+                ErrorCode::InternalError
+            }
+            ClientError::Topic(_) => ErrorCode::InvalidTopic,
+            ClientError::IdleTimeout { .. } => ErrorCode::IdleTimeout,
+        }
+    }
+}
+
+impl From<CodecError> for ClientError {
+    fn from(error: CodecError) -> Self {
+        ClientError::Codec(ServerCodecError::Codec(error))
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(error: io::Error) -> Self {
+        ClientError::Codec(ServerCodecError::Io(error))
+    }
 }
 
 impl From<mpsc::error::SendError<OutboundMessage>> for ClientError {
@@ -59,84 +123,248 @@ impl std::fmt::Display for ClientId {
     }
 }
 
+/// Server-wide services shared by every client session.
+#[derive(Clone)]
+pub struct SessionServices {
+    pub authenticator: Arc<dyn Authenticator>,
+    pub clock: Arc<dyn Clock>,
+    pub interceptors: Arc<InterceptorChain>,
+    /// Drops republished duplicates by `Ocypode-Msg-Id`; `None` when deduplication is off.
+    pub dedup: Option<Arc<DedupWindow>>,
+    /// Records every inbound and outbound frame when frame tracing is enabled.
+    #[cfg(feature = "trace")]
+    pub tracer: Option<Arc<FrameTracer>>,
+}
+
+impl SessionServices {
+    /// Services with the system clock and no interceptors.
+    pub fn new(authenticator: Arc<dyn Authenticator>) -> Self {
+        Self {
+            authenticator,
+            clock: Arc::new(SystemClock),
+            interceptors: Arc::new(InterceptorChain::new()),
+            dedup: None,
+            #[cfg(feature = "trace")]
+            tracer: None,
+        }
+    }
+}
+
 /// Server-side representation of a connected client.
 /// Created immediately after the QUIC stream is accepted, before the handshake.
 pub struct Client<R: AsyncRead + Unpin + Send> {
     client_id: ClientId,
     /// Read buffer (FramedRead holds a 32 KiB byte buffer internally).
-    framed_read: FramedRead<R, ServerCodec>,
+    framed_read: FramedRead<R, BudgetedDecoder<ServerCodec>>,
     /// Sender end of the outbound write-buffer channel.
     /// The writer task drains this channel and batch-flushes to the network.
     outbound_sender: mpsc::Sender<OutboundMessage>,
-    authenticator: Arc<dyn Authenticator>,
+    /// Largest payload the client accepts, shared with the writer task.
+    /// Starts at the server maximum and is lowered if CONNECT requests a smaller value.
+    max_payload: Arc<AtomicU32>,
+    /// Whether deliveries keep `received_at_ms`, shared with the writer task.
+    /// Set from CONNECT's `receive_timestamps`.
+    receive_timestamps: Arc<AtomicBool>,
+    /// Auto-unsubscribe countdowns and FLOW credits, set here and consumed by the writer task.
+    delivery: Arc<DeliveryControls>,
+    services: SessionServices,
     config: Arc<ServerConfig>,
 }
 
+/// Per-client constraints the writer task applies before a delivery hits the wire.
+struct DeliveryLimits {
+    message_ttl_ms: Option<u64>,
+    max_payload: Arc<AtomicU32>,
+    receive_timestamps: Arc<AtomicBool>,
+    delivery: Arc<DeliveryControls>,
+    max_paused_deliveries: usize,
+    redelivery_timeout_ms: u64,
+    max_inflight_deliveries: usize,
+}
+
 impl<R: AsyncRead + Unpin + Send + 'static> Client<R> {
     /// Constructs a client from any Transport.
     /// Spawns an internal writer task that owns FramedWrite and the outbound channel receiver.
+    #[allow(dead_code)]
     pub fn new<T: Transport<Reader = R>>(
         transport: T,
         authenticator: Arc<dyn Authenticator>,
         config: Arc<ServerConfig>,
+    ) -> Self {
+        let read_budget = ReadBudget::new(config.quic.connection_read_budget);
+        Self::with_services(transport, SessionServices::new(authenticator), config, read_budget)
+    }
+
+    /// Constructs a client backed by the given server-wide services.
+    /// `read_budget` is shared by every stream of the same connection.
+    pub fn with_services<T: Transport<Reader = R>>(
+        transport: T,
+        services: SessionServices,
+        config: Arc<ServerConfig>,
+        read_budget: ReadBudget,
     ) -> Self {
         let client_id = ClientId::new();
         let (reader, writer) = transport.into_split();
-        let framed_read =
-            FramedRead::with_capacity(reader, ServerCodec, config.quic.read_buffer_size);
-        let framed_write =
-            FramedWrite::with_capacity(writer, ServerCodec, config.quic.write_buffer_size);
+        let framed_read = FramedRead::with_capacity(
+            reader,
+            BudgetedDecoder::new(
+                ServerCodec::default()
+                    .with_max_header_bytes(config.quic.max_header_bytes as usize)
+                    .with_max_frame_payload(config.quic.max_frame_payload),
+                read_budget,
+            ),
+            config.quic.read_buffer_size,
+        );
+        let framed_write = FramedWrite::with_capacity(
+            writer,
+            ServerCodec::default(),
+            config.quic.write_buffer_size,
+        );
 
         let (outbound_sender, outbound_receiver) =
             mpsc::channel(config.quic.outbound_channel_capacity);
-        tokio::spawn(run_outbound_writer(framed_write, outbound_receiver));
+        let max_payload = Arc::new(AtomicU32::new(MAXIMUM_PAYLOAD_BYTES as u32));
+        let receive_timestamps = Arc::new(AtomicBool::new(false));
+        let delivery = Arc::new(DeliveryControls::default());
+        let limits = DeliveryLimits {
+            message_ttl_ms: config.quic.outbound_message_ttl_ms,
+            max_payload: Arc::clone(&max_payload),
+            receive_timestamps: Arc::clone(&receive_timestamps),
+            delivery: Arc::clone(&delivery),
+            max_paused_deliveries: config.quic.max_paused_deliveries,
+            redelivery_timeout_ms: config.quic.redelivery_timeout_ms,
+            max_inflight_deliveries: config.quic.max_inflight_deliveries,
+        };
+        tokio::spawn(run_outbound_writer(
+            framed_write,
+            outbound_receiver,
+            services.clone(),
+            limits,
+        ));
 
-        Self { client_id, framed_read, outbound_sender, authenticator, config }
+        Self {
+            client_id,
+            framed_read,
+            outbound_sender,
+            max_payload,
+            receive_timestamps,
+            delivery,
+            services,
+            config,
+        }
     }
 
     /// Runs the full client pipeline: handshake then frame dispatch.
     pub async fn run(mut self) -> Result<(), ClientError> {
         // Build INFO once from ServerConfig before entering the handshake.
-        let info = ServerOutbound::info(
-            PROTOCOL_VERSION,
-            self.client_id,
-            self.config.server_id.clone(),
-            self.config.server_name.clone(),
-            self.config.requires_auth,
-            self.config.tls_verify,
-        );
+        let nonce = generate_nonce()?.to_vec();
+        let info = pb::Info {
+            max_keepalive_secs: self.config.quic.max_keepalive_secs,
+            nonce: nonce.clone(),
+            topic_alias_maximum: self.config.quic.topic_alias_maximum,
+            max_reassembled_payload: self.config.quic.max_reassembled_payload,
+            connect_urls: self.config.connect_urls.clone(),
+            max_header_bytes: Some(self.config.quic.max_header_bytes),
+            ..ServerOutbound::info(
+                PROTOCOL_VERSION,
+                self.client_id,
+                self.config.server_id.clone(),
+                self.config.server_name.clone(),
+                self.config.requires_auth,
+                self.config.tls_verify,
+            )
+        };
 
         // Phase 1: Handshake
         let completed = perform_handshake(
             &mut self.framed_read,
             &self.outbound_sender,
             self.config.quic.connect_timeout,
-            PendingHandshake::new(self.client_id),
-            self.authenticator.as_ref(),
+            PendingHandshake::new(self.client_id, nonce),
+            self.services.authenticator.as_ref(),
             info,
         )
         .await?;
-        tracing::info!("client_id={} connection established", completed.client_id);
+        tracing::info!(
+            "client_id={} client_name={:?} connection established",
+            completed.client_id,
+            completed.connect_info.client_name.as_deref().unwrap_or_default()
+        );
+        if let Some(requested) = completed.connect_info.max_payload {
+            self.max_payload.fetch_min(requested, Ordering::Relaxed);
+        }
+        self.receive_timestamps.store(completed.connect_info.receive_timestamps, Ordering::Relaxed);
+
+        let idle_timeout = idle_timeout(
+            completed.connect_info.keepalive_secs,
+            self.config.quic.max_keepalive_secs,
+        );
+
+        let mut topic_aliases = TopicAliasTable::new(self.config.quic.topic_alias_maximum);
+        let mut fragments = Reassembler::new(self.config.quic.max_reassembled_payload as usize);
 
         // Phase 2: Frame dispatch loop (hot path)
-        while let Some(frame) = self.framed_read.next().await {
-            dispatch_frame(frame?, &completed, &self.outbound_sender)?;
+        loop {
+            let next = match idle_timeout {
+                Some(limit) => tokio::time::timeout(limit, self.framed_read.next())
+                    .await
+                    .map_err(|_| ClientError::IdleTimeout { timeout: limit })?,
+                None => self.framed_read.next().await,
+            };
+            let Some(frame) = next else { break };
+            let frame = frame?;
+            // Display omits credentials, payloads and headers.
+            tracing::debug!("client_id={} received {}", completed.client_id, frame);
+            #[cfg(feature = "trace")]
+            if let Some(tracer) = &self.services.tracer {
+                let _ = tracer.record_inbound(&frame);
+            }
+            if let Frame::Disconnect(disconnect) = &frame {
+                tracing::info!(
+                    "client_id={} disconnected: {:?}",
+                    completed.client_id,
+                    disconnect.reason()
+                );
+                break;
+            }
+            dispatch_frame(
+                frame,
+                &completed,
+                &mut topic_aliases,
+                &mut fragments,
+                &self.delivery,
+                &self.services,
+                &self.outbound_sender,
+            )
+            .await?;
         }
 
         Ok(())
     }
 }
 
+/// Silence tolerated before closing, in thousandths of the negotiated keepalive interval.
+const IDLE_TIMEOUT_PER_KEEPALIVE_MILLIS: u64 = 1500;
+
+/// How long a connection may stay silent: one and a half keepalive intervals, with the
+/// client's request capped at `max_keepalive_secs`. `None` when the client asked for none or
+/// the server caps keepalives at zero.
+fn idle_timeout(requested_secs: Option<u32>, max_keepalive_secs: u32) -> Option<Duration> {
+    let keepalive_secs = requested_secs?.min(max_keepalive_secs);
+    if keepalive_secs == 0 {
+        return None;
+    }
+    Some(Duration::from_millis(u64::from(keepalive_secs) * IDLE_TIMEOUT_PER_KEEPALIVE_MILLIS))
+}
+
 async fn perform_handshake<R: AsyncRead + Unpin>(
-    framed_read: &mut FramedRead<R, ServerCodec>,
+    framed_read: &mut FramedRead<R, BudgetedDecoder<ServerCodec>>,
     outbound: &mpsc::Sender<OutboundMessage>,
     connect_timeout_ms: u64,
     pending: PendingHandshake,
     authenticator: &dyn Authenticator,
     info: pb::Info,
 ) -> Result<CompletedHandshake, ClientError> {
-    use std::time::Duration;
-
     use tokio::time::timeout;
 
     outbound.send(OutboundMessage::Info(info)).await?;
@@ -156,10 +384,14 @@ async fn perform_handshake<R: AsyncRead + Unpin>(
     .map_err(|_| ClientError::Handshake(HandshakeError::ConnectTimeout))?
 }
 
-fn dispatch_frame(
+async fn dispatch_frame(
     frame: Frame,
     handshake: &CompletedHandshake,
-    _outbound: &mpsc::Sender<OutboundMessage>,
+    topic_aliases: &mut TopicAliasTable,
+    fragments: &mut Reassembler<pb::Publish>,
+    delivery: &DeliveryControls,
+    services: &SessionServices,
+    outbound: &mpsc::Sender<OutboundMessage>,
 ) -> Result<(), ClientError> {
     match frame {
         Frame::Connect(_) => {
@@ -168,24 +400,191 @@ fn dispatch_frame(
                 handshake.client_id
             );
         }
-        // TODO: permission check → router dispatch
-        Frame::Publish(_) | Frame::Subscribe(_) | Frame::UnSubscribe(_) => {}
+        Frame::Ping(_) => outbound.send(OutboundMessage::Pong(pb::Pong {})).await?,
+        Frame::Publish(mut publish) => {
+            let publish_id = publish.publish_id;
+            let reassembled = match topic_aliases.resolve(&mut publish) {
+                Ok(()) => fragments.push(publish).map_err(|error| {
+                    let code = match error {
+                        FragmentError::TooLarge { .. } => ErrorCode::PayloadTooLarge,
+                        _ => ErrorCode::ProtocolViolation,
+                    };
+                    (code, error.to_string())
+                }),
+                Err(error) => Err((ErrorCode::InvalidTopic, error.to_string())),
+            };
+            // Fragments before the last are only buffered; the reply answers the whole Publish.
+            let accepted = match reassembled {
+                Ok(None) => return Ok(()),
+                Ok(Some(publish)) => match Topic::try_from(publish.topic.clone()) {
+                    Ok(_) => Ok(publish),
+                    Err(error) => Err((ErrorCode::InvalidTopic, error.to_string())),
+                },
+                Err(rejection) => Err(rejection),
+            };
+            // Interceptors run before the reply so a dropped Publish is not acknowledged.
+            let accepted = accepted.and_then(|mut publish| {
+                match services.interceptors.on_publish(&mut publish) {
+                    Action::Continue => Ok(publish),
+                    Action::Drop => {
+                        Err((ErrorCode::PermissionViolation, PUBLISH_DROPPED_REASON.to_string()))
+                    }
+                }
+            });
+            // TODO: permission check, before deduplication so an unauthorized client cannot
+            //       record message ids on the topic.
+            // Duplicates are acknowledged like the original, so a retrying producer sees success.
+            let now_ms = services.clock.now_ms();
+            let duplicate = accepted.as_ref().is_ok_and(|publish| {
+                services.dedup.as_ref().is_some_and(|dedup| dedup.is_duplicate(publish, now_ms))
+            });
+            if handshake.connect_info.verbose {
+                let reply = match &accepted {
+                    Ok(_) => OutboundMessage::Ack(ServerOutbound::ack(publish_id)),
+                    Err((code, reason)) => OutboundMessage::Error(ServerOutbound::error(
+                        publish_id,
+                        *code,
+                        Some(reason.clone()),
+                    )),
+                };
+                outbound.send(reply).await?;
+            }
+            if accepted.is_err() || duplicate {
+                return Ok(());
+            }
+            // TODO: router dispatch, via `Router::search_without_echo` when
+            // `handshake.connect_info.no_echo` is set.
+        }
+        Frame::Subscribe(subscribe) => {
+            let accepted = TopicFilter::new(BytesMut::from(&subscribe.topic[..]));
+            if accepted.is_ok() {
+                delivery.subscribe(subscribe.subscription_id, subscribe.max_msgs, subscribe.qos());
+            }
+            if handshake.connect_info.verbose {
+                let reply = match accepted {
+                    Ok(_) => ServerOutbound::sub_ack(subscribe.subscription_id),
+                    Err(_) => ServerOutbound::sub_denied(
+                        subscribe.subscription_id,
+                        ErrorCode::InvalidTopic,
+                    ),
+                };
+                outbound.send(OutboundMessage::SubAck(reply)).await?;
+            }
+            // TODO: permission check → router dispatch
+        }
+        Frame::UnSubscribe(unsubscribe) => {
+            // Deliveries stop at the writer, so messages already queued are cut off too.
+            let subscription_id = unsubscribe.subscription_id;
+            if delivery.unsubscribe(subscription_id, unsubscribe.max_msgs) {
+                outbound.send(OutboundMessage::SubscriptionClosed { subscription_id }).await?
+            }
+            // TODO: permission check → router removal once the countdown runs out
+        }
+        Frame::Flow(flow) => {
+            // FLOW for a subscription this connection does not hold is ignored.
+            if delivery.credits.grant(flow.subscription_id, flow.credits) {
+                let subscription_id = flow.subscription_id;
+                outbound.send(OutboundMessage::CreditsGranted { subscription_id }).await?
+            }
+        }
+        Frame::MessageAck(ack) => {
+            let delivery_id = ack.delivery_id;
+            outbound.send(OutboundMessage::DeliveryAcknowledged { delivery_id }).await?
+        }
+        // The dispatch loop ends the session before a Disconnect gets here.
+        Frame::Disconnect(_) => {}
+        // Unclaimed extensions are ignored so peers can add commands without breaking this one.
+        Frame::Extension(extension) => {
+            if services.interceptors.on_extension(&extension) == Action::Continue {
+                tracing::debug!(
+                    "client_id={} ignoring unhandled extension command={}",
+                    handshake.client_id,
+                    extension.command
+                );
+            }
+        }
     }
     Ok(())
 }
 
+/// Sequence number stamped on the first Message written to a connection.
+const FIRST_MESSAGE_SEQUENCE: u64 = 1;
+/// How many times per redelivery timeout the writer looks for overdue deliveries, so one is
+/// resent at most a quarter of the timeout late.
+const REDELIVERY_CHECKS_PER_TIMEOUT: u64 = 4;
+
+/// Delivery state only the writer task touches.
+struct WriterState {
+    paused: PausedDeliveries,
+    inflight: InflightTracker,
+    next_sequence: u64,
+}
+
+impl WriterState {
+    fn new(limits: &DeliveryLimits) -> Self {
+        Self {
+            paused: PausedDeliveries::new(limits.max_paused_deliveries),
+            inflight: InflightTracker::new(
+                limits.redelivery_timeout_ms,
+                limits.max_inflight_deliveries,
+            ),
+            next_sequence: FIRST_MESSAGE_SEQUENCE,
+        }
+    }
+}
+
 /// Drains the outbound channel and batch-flushes to FramedWrite.
 /// Minimizes syscall overhead by coalescing multiple messages into a single flush.
+/// Between batches, and on a timer while the channel is idle, overdue at-least-once
+/// deliveries are sent again.
 async fn run_outbound_writer<W: AsyncWrite + Unpin>(
     mut framed_write: FramedWrite<W, ServerCodec>,
     mut receiver: mpsc::Receiver<OutboundMessage>,
+    services: SessionServices,
+    limits: DeliveryLimits,
 ) {
-    while let Some(message) = receiver.recv().await {
-        let _ = dispatch_outbound(&mut framed_write, message).await;
+    let mut state = WriterState::new(&limits);
+    let mut redelivery_check = tokio::time::interval(Duration::from_millis(
+        (limits.redelivery_timeout_ms / REDELIVERY_CHECKS_PER_TIMEOUT).max(1),
+    ));
+    redelivery_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        let received = tokio::select! {
+            received = receiver.recv() => match received {
+                Some(message) => Some(message),
+                None => break,
+            },
+            _ = redelivery_check.tick() => None,
+        };
+        let now_ms = services.clock.now_ms();
+        if let Some(message) = received {
+            let _ = dispatch_outbound(
+                &mut framed_write,
+                message,
+                now_ms,
+                &limits,
+                &services,
+                &mut state,
+            )
+            .await;
+
+            // Non-blocking drain: feed all queued messages before flushing.
+            while let Ok(message) = receiver.try_recv() {
+                let _ = dispatch_outbound(
+                    &mut framed_write,
+                    message,
+                    now_ms,
+                    &limits,
+                    &services,
+                    &mut state,
+                )
+                .await;
+            }
+        }
 
-        // Non-blocking drain: feed all queued messages before flushing.
-        while let Ok(message) = receiver.try_recv() {
-            let _ = dispatch_outbound(&mut framed_write, message).await;
+        let is_open = |subscription_id| limits.delivery.is_open(subscription_id);
+        for message in state.inflight.take_redeliveries(now_ms, is_open) {
+            let _ = send_sequenced(&mut framed_write, message, &services, &mut state).await;
         }
 
         // One flush per batch → minimizes syscalls.
@@ -197,32 +596,151 @@ async fn run_outbound_writer<W: AsyncWrite + Unpin>(
 async fn dispatch_outbound<W: AsyncWrite + Unpin>(
     framed_write: &mut FramedWrite<W, ServerCodec>,
     message: OutboundMessage,
+    now_ms: u64,
+    limits: &DeliveryLimits,
+    services: &SessionServices,
+    state: &mut WriterState,
 ) -> Result<(), ServerCodecError> {
     match message {
-        OutboundMessage::Info(info) => framed_write.feed(info).await?,
-        // TODO: Message delivery to subscribers
-        OutboundMessage::Message(_) => {}
+        OutboundMessage::Info(info) => feed(framed_write, info, services).await?,
+        OutboundMessage::Ack(ack) => feed(framed_write, ack, services).await?,
+        OutboundMessage::Error(error) => feed(framed_write, error, services).await?,
+        OutboundMessage::Pong(pong) => feed(framed_write, pong, services).await?,
+        OutboundMessage::SubAck(sub_ack) => feed(framed_write, sub_ack, services).await?,
+        OutboundMessage::Disconnect(disconnect) => feed(framed_write, disconnect, services).await?,
+        OutboundMessage::Extension(extension) => feed(framed_write, extension, services).await?,
+        OutboundMessage::Message { mut message, enqueued_at_ms, publish_qos } => {
+            if limits.message_ttl_ms.is_some_and(|ttl| now_ms.saturating_sub(enqueued_at_ms) > ttl)
+            {
+                return Ok(());
+            }
+            if !limits.receive_timestamps.load(Ordering::Relaxed) {
+                message.received_at_ms = None;
+            }
+            // Interceptors may rewrite the message, so the size check sees what they return.
+            if services.interceptors.on_deliver(&mut message) == Action::Drop {
+                return Ok(());
+            }
+            let payload_size = message.payload.len() + message.header.len();
+            if payload_size > limits.max_payload.load(Ordering::Relaxed) as usize {
+                return Ok(());
+            }
+            let subscription_id = message.subscription_id;
+            // Once paused, later deliveries queue behind the held ones to keep their order.
+            // A subscription that ended while paused gets nothing more held.
+            if state.paused.is_paused(subscription_id) {
+                if limits.delivery.is_open(subscription_id) {
+                    state.paused.hold(message, publish_qos);
+                }
+                return Ok(());
+            }
+            match limits.delivery.try_admit(subscription_id) {
+                Admission::Deliver { qos } => {
+                    let qos = effective_qos(publish_qos, qos);
+                    deliver(framed_write, message, qos, now_ms, services, state).await?
+                }
+                Admission::NoCredit => {
+                    state.paused.hold(message, publish_qos);
+                }
+                Admission::Exhausted => {}
+            }
+        }
+        OutboundMessage::CreditsGranted { subscription_id } => {
+            for (message, qos) in state.paused.release(subscription_id, &limits.delivery) {
+                deliver(framed_write, message, qos, now_ms, services, state).await?;
+            }
+        }
+        OutboundMessage::SubscriptionClosed { subscription_id } => {
+            state.paused.discard(subscription_id);
+            state.inflight.forget_subscription(subscription_id);
+        }
+        OutboundMessage::DeliveryAcknowledged { delivery_id } => {
+            state.inflight.ack(delivery_id);
+        }
     }
     Ok(())
 }
 
+/// Tracks an admitted Message according to its QoS, then writes it. An at-least-once
+/// delivery that finds the inflight tracker full is dropped.
+async fn deliver<W: AsyncWrite + Unpin>(
+    framed_write: &mut FramedWrite<W, ServerCodec>,
+    mut message: pb::Message,
+    qos: pb::QosLevel,
+    now_ms: u64,
+    services: &SessionServices,
+    state: &mut WriterState,
+) -> Result<(), ServerCodecError> {
+    if !state.inflight.on_deliver(qos, &mut message, now_ms) {
+        return Ok(());
+    }
+    send_sequenced(framed_write, message, services, state).await
+}
+
+/// Stamps the next sequence on a Message, first delivery or redelivery alike, and queues it.
+async fn send_sequenced<W: AsyncWrite + Unpin>(
+    framed_write: &mut FramedWrite<W, ServerCodec>,
+    mut message: pb::Message,
+    services: &SessionServices,
+    state: &mut WriterState,
+) -> Result<(), ServerCodecError> {
+    message.sequence = Some(state.next_sequence);
+    state.next_sequence += 1;
+    feed(framed_write, message, services).await
+}
+
+/// Queues `item` on the writer, recording it first when frame tracing is enabled.
+async fn feed<W: AsyncWrite + Unpin, T: CommandCodec>(
+    framed_write: &mut FramedWrite<W, ServerCodec>,
+    item: T,
+    services: &SessionServices,
+) -> Result<(), ServerCodecError> {
+    #[cfg(feature = "trace")]
+    if let Some(tracer) = &services.tracer {
+        let _ = tracer.record(TraceDirection::Outbound, &item);
+    }
+    #[cfg(not(feature = "trace"))]
+    let _ = services;
+    framed_write.feed(item).await
+}
+
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{
+        sync::{Arc, atomic::AtomicU32},
+        time::Duration,
+    };
 
+    use bytes::{BufMut, Bytes};
     use futures_util::SinkExt;
-    use tokio::io::{AsyncRead, AsyncWrite};
+    use prost::Message;
+    use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
     use tokio_stream::StreamExt;
     use tokio_util::codec::{FramedRead, FramedWrite};
 
-    use super::Client;
+    use super::{
+        Client, ClientError, DeliveryLimits, SessionServices, WriterState, dispatch_outbound,
+        idle_timeout, run_outbound_writer,
+    };
     use crate::{
         auth::NoAuthAuthenticator,
+        clock::{Clock, MockClock},
         config::ServerConfig,
-        parser::{ClientCodec, ClientFrame, ClientOutbound},
+        delivery::DeliveryControls,
+        error::{CodecError, ErrorCode, ServerCodecError, TopicError},
+        handshake::HandshakeError,
+        interceptor::{Action, Interceptor, InterceptorChain},
+        parser::{
+            ClientCodec, ClientFrame, ClientOutbound, Command, MAXIMUM_PAYLOAD_BYTES,
+            OutboundMessage, ReadBudget, ServerCodec, ServerOutbound, pb,
+        },
         transport::Transport,
     };
 
+    const MAX_PAUSED_DELIVERIES: usize = 8;
+    const REDELIVERY_TIMEOUT_MS: u64 = 1_000;
+    const MAX_INFLIGHT_DELIVERIES: usize = 8;
+
     struct DuplexTransport<R, W> {
         reader: R,
         writer: W,
@@ -241,6 +759,15 @@ mod tests {
         }
     }
 
+    /// Delivery controls with each of `subscription_ids` subscribed and unlimited.
+    fn subscribed(subscription_ids: &[u32]) -> Arc<DeliveryControls> {
+        let delivery = DeliveryControls::default();
+        for &subscription_id in subscription_ids {
+            delivery.subscribe(subscription_id, None, pb::QosLevel::AtMostOnce);
+        }
+        Arc::new(delivery)
+    }
+
     #[tokio::test]
     async fn client_run_sends_info_and_accepts_connect() {
         let (client_io, server_io) = tokio::io::duplex(4096);
@@ -253,12 +780,12 @@ mod tests {
         let server = tokio::spawn(client.run());
 
         // Act as a network client: read INFO, send CONNECT.
-        let mut framed_read = FramedRead::with_capacity(client_rx, ClientCodec, 4096);
+        let mut framed_read = FramedRead::with_capacity(client_rx, ClientCodec::default(), 4096);
         let frame = framed_read.next().await.unwrap().unwrap();
         let ClientFrame::Info(info_msg) = frame else { panic!("expected Info frame") };
         assert!(info_msg.client_id > 0);
 
-        let mut framed_write = FramedWrite::with_capacity(client_tx, ClientCodec, 4096);
+        let mut framed_write = FramedWrite::with_capacity(client_tx, ClientCodec::default(), 4096);
         framed_write.send(ClientOutbound::connect(1, false)).await.unwrap();
 
         // Drop the write end to signal EOF → server run() should finish cleanly.
@@ -267,4 +794,641 @@ mod tests {
 
         server.await.unwrap().unwrap();
     }
+
+    #[test]
+    fn idle_timeout_is_one_and_a_half_keepalives() {
+        assert_eq!(idle_timeout(Some(10), 60), Some(Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn idle_timeout_caps_keepalive_at_server_maximum() {
+        assert_eq!(idle_timeout(Some(600), 60), Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn idle_timeout_is_disabled_without_keepalive() {
+        assert_eq!(idle_timeout(None, 60), None);
+    }
+
+    #[test]
+    fn idle_timeout_is_disabled_for_zero_keepalive() {
+        assert_eq!(idle_timeout(Some(0), 60), None);
+    }
+
+    #[test]
+    fn idle_timeout_is_disabled_when_server_maximum_is_zero() {
+        assert_eq!(idle_timeout(Some(10), 0), None);
+    }
+
+    #[tokio::test]
+    async fn client_run_ends_on_disconnect() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (client_rx, client_tx) = tokio::io::split(client_io);
+
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client =
+            Client::new(transport, Arc::new(NoAuthAuthenticator), Arc::new(ServerConfig::new()));
+        let server = tokio::spawn(client.run());
+
+        let mut framed_read = FramedRead::new(client_rx, ClientCodec::default());
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_read.next().await.unwrap().unwrap();
+        framed_write.send(ClientOutbound::connect(1, false)).await.unwrap();
+        framed_write.send(pb::Disconnect::default()).await.unwrap();
+
+        // The write half stays open, so only the Disconnect can end the session.
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn client_run_closes_silent_connection_with_idle_timeout() {
+        const KEEPALIVE_SECS: u32 = 10;
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (client_rx, client_tx) = tokio::io::split(client_io);
+
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client =
+            Client::new(transport, Arc::new(NoAuthAuthenticator), Arc::new(ServerConfig::new()));
+        let server = tokio::spawn(client.run());
+
+        let mut framed_read = FramedRead::new(client_rx, ClientCodec::default());
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_read.next().await.unwrap().unwrap();
+        let connect = pb::Connect {
+            keepalive_secs: Some(KEEPALIVE_SECS),
+            ..ClientOutbound::connect(1, false)
+        };
+        framed_write.send(connect).await.unwrap();
+
+        // Paused time jumps ahead while the connection is silent, past 1.5 keepalives.
+        let result = server.await.unwrap();
+
+        assert!(matches!(result, Err(ClientError::IdleTimeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn verbose_client_gets_denied_sub_ack_for_invalid_filter() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (client_rx, client_tx) = tokio::io::split(client_io);
+
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client =
+            Client::new(transport, Arc::new(NoAuthAuthenticator), Arc::new(ServerConfig::new()));
+        tokio::spawn(client.run());
+
+        let mut framed_read = FramedRead::new(client_rx, ClientCodec::default());
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_read.next().await.unwrap().unwrap();
+        framed_write.send(ClientOutbound::connect(1, true)).await.unwrap();
+        let subscribe =
+            pb::Subscribe { topic: b"a/#/b".to_vec(), subscription_id: 7, ..Default::default() };
+        framed_write.send(subscribe).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(
+            frame,
+            ClientFrame::SubAck(ServerOutbound::sub_denied(7, ErrorCode::InvalidTopic))
+        );
+    }
+
+    struct DropAllPublishes;
+
+    impl Interceptor for DropAllPublishes {
+        fn on_publish(&self, _publish: &mut pb::Publish) -> Action {
+            Action::Drop
+        }
+    }
+
+    #[tokio::test]
+    async fn verbose_client_gets_error_for_publish_dropped_by_interceptor() {
+        const PUBLISH_ID: u64 = 5;
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (client_rx, client_tx) = tokio::io::split(client_io);
+
+        let mut interceptors = InterceptorChain::new();
+        interceptors.register(Arc::new(DropAllPublishes));
+        let services = SessionServices {
+            interceptors: Arc::new(interceptors),
+            ..SessionServices::new(Arc::new(NoAuthAuthenticator))
+        };
+        let config = Arc::new(ServerConfig::new());
+        let read_budget = ReadBudget::new(config.quic.connection_read_budget);
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client = Client::with_services(transport, services, config, read_budget);
+        tokio::spawn(client.run());
+
+        let mut framed_read = FramedRead::new(client_rx, ClientCodec::default());
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_read.next().await.unwrap().unwrap();
+        framed_write.send(ClientOutbound::connect(1, true)).await.unwrap();
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            publish_id: PUBLISH_ID,
+            ..Default::default()
+        };
+        framed_write.send(publish).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        let ClientFrame::Error(error) = frame else { panic!("expected Error, got {frame:?}") };
+        assert_eq!(error.publish_id, PUBLISH_ID);
+        assert_eq!(error.code, ErrorCode::PermissionViolation as u32);
+    }
+
+    #[tokio::test]
+    async fn client_run_answers_ping_with_pong() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (client_rx, client_tx) = tokio::io::split(client_io);
+
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client =
+            Client::new(transport, Arc::new(NoAuthAuthenticator), Arc::new(ServerConfig::new()));
+        tokio::spawn(client.run());
+
+        let mut framed_read = FramedRead::new(client_rx, ClientCodec::default());
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_read.next().await.unwrap().unwrap();
+        framed_write.send(ClientOutbound::connect(1, false)).await.unwrap();
+        framed_write.send(pb::Ping {}).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(frame, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    /// Limits for a test writer: no TTL, no payload cap beyond the server maximum, and
+    /// `delivery` as given. Tests override single fields with struct update syntax.
+    fn writer_limits(delivery: Arc<DeliveryControls>) -> DeliveryLimits {
+        DeliveryLimits {
+            message_ttl_ms: None,
+            max_payload: Arc::new(AtomicU32::new(MAXIMUM_PAYLOAD_BYTES as u32)),
+            receive_timestamps: Arc::default(),
+            delivery,
+            max_paused_deliveries: MAX_PAUSED_DELIVERIES,
+            redelivery_timeout_ms: REDELIVERY_TIMEOUT_MS,
+            max_inflight_deliveries: MAX_INFLIGHT_DELIVERIES,
+        }
+    }
+
+    /// Runs a writer with `limits` on a mock clock at 0.
+    fn spawn_writer(
+        limits: DeliveryLimits,
+    ) -> (
+        tokio::sync::mpsc::Sender<OutboundMessage>,
+        FramedRead<tokio::io::DuplexStream, ClientCodec>,
+        Arc<MockClock>,
+    ) {
+        spawn_writer_with_interceptors(limits, InterceptorChain::new())
+    }
+
+    /// Runs a writer with `limits` and `interceptors` on a mock clock at 0.
+    fn spawn_writer_with_interceptors(
+        limits: DeliveryLimits,
+        interceptors: InterceptorChain,
+    ) -> (
+        tokio::sync::mpsc::Sender<OutboundMessage>,
+        FramedRead<tokio::io::DuplexStream, ClientCodec>,
+        Arc<MockClock>,
+    ) {
+        let (server_io, client_io) = tokio::io::duplex(4096);
+        let (sender, receiver) = tokio::sync::mpsc::channel(8);
+        let clock = Arc::new(MockClock::new(0));
+        let services = SessionServices {
+            clock: Arc::clone(&clock) as Arc<dyn Clock>,
+            interceptors: Arc::new(interceptors),
+            ..SessionServices::new(Arc::new(NoAuthAuthenticator))
+        };
+        let framed_write = FramedWrite::new(server_io, ServerCodec::default());
+        tokio::spawn(run_outbound_writer(framed_write, receiver, services, limits));
+        (sender, FramedRead::new(client_io, ClientCodec::default()), clock)
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_drops_message_after_clock_passes_ttl() {
+        const MESSAGE_TTL_MS: u64 = 100;
+        let limits = DeliveryLimits {
+            message_ttl_ms: Some(MESSAGE_TTL_MS),
+            ..writer_limits(subscribed(&[1, 2]))
+        };
+        let (sender, mut framed_read, clock) = spawn_writer(limits);
+
+        let delivery = |subscription_id, enqueued_at_ms| OutboundMessage::Message {
+            message: pb::Message { subscription_id, ..Default::default() },
+            enqueued_at_ms,
+            publish_qos: pb::QosLevel::AtMostOnce,
+        };
+        let stale = delivery(1, clock.now_ms());
+        clock.advance(MESSAGE_TTL_MS + 1);
+        sender.send(stale).await.unwrap();
+        sender.send(delivery(2, clock.now_ms())).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        let ClientFrame::Message(message) = frame else { panic!("expected Message frame") };
+        assert_eq!(message.subscription_id, 2);
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_skips_message_above_client_max_payload() {
+        const CLIENT_MAX_PAYLOAD: u32 = 4;
+        let limits = DeliveryLimits {
+            max_payload: Arc::new(AtomicU32::new(CLIENT_MAX_PAYLOAD)),
+            ..writer_limits(subscribed(&[1, 2]))
+        };
+        let (sender, mut framed_read, _) = spawn_writer(limits);
+
+        let delivery = |subscription_id, payload: &[u8]| OutboundMessage::Message {
+            message: pb::Message {
+                subscription_id,
+                payload: Bytes::copy_from_slice(payload),
+                ..Default::default()
+            },
+            enqueued_at_ms: 0,
+            publish_qos: pb::QosLevel::AtMostOnce,
+        };
+        sender.send(delivery(1, b"oversized")).await.unwrap();
+        sender.send(delivery(2, b"fits")).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        let ClientFrame::Message(message) = frame else { panic!("expected Message frame") };
+        assert_eq!(message.subscription_id, 2);
+    }
+
+    struct ClearPayloads;
+
+    impl Interceptor for ClearPayloads {
+        fn on_deliver(&self, message: &mut pb::Message) -> Action {
+            message.payload = Bytes::new();
+            Action::Continue
+        }
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_checks_max_payload_after_interceptors() {
+        const CLIENT_MAX_PAYLOAD: u32 = 4;
+        let limits = DeliveryLimits {
+            max_payload: Arc::new(AtomicU32::new(CLIENT_MAX_PAYLOAD)),
+            ..writer_limits(subscribed(&[1]))
+        };
+        let mut interceptors = InterceptorChain::new();
+        interceptors.register(Arc::new(ClearPayloads));
+        let (sender, mut framed_read, _) = spawn_writer_with_interceptors(limits, interceptors);
+
+        let message = pb::Message {
+            subscription_id: 1,
+            payload: Bytes::from_static(b"oversized"),
+            ..Default::default()
+        };
+        let delivery = OutboundMessage::Message {
+            message,
+            enqueued_at_ms: 0,
+            publish_qos: pb::QosLevel::AtMostOnce,
+        };
+        sender.send(delivery).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert!(matches!(frame, ClientFrame::Message(message) if message.payload.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_strips_receive_timestamp_unless_requested() {
+        const RECEIVED_AT_MS: u64 = 1_700_000_000_000;
+        let (sender, mut framed_read, _) = spawn_writer(writer_limits(subscribed(&[0])));
+
+        let message = pb::Message { received_at_ms: Some(RECEIVED_AT_MS), ..Default::default() };
+        sender
+            .send(OutboundMessage::Message {
+                message,
+                enqueued_at_ms: 0,
+                publish_qos: pb::QosLevel::AtMostOnce,
+            })
+            .await
+            .unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        let ClientFrame::Message(message) = frame else { panic!("expected Message frame") };
+        assert_eq!(message.received_at_ms, None);
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_numbers_messages_consecutively() {
+        const DELIVERY_COUNT: usize = 2;
+        let (sender, mut framed_read, _) = spawn_writer(writer_limits(subscribed(&[0])));
+
+        for _ in 0..DELIVERY_COUNT {
+            let delivery = OutboundMessage::Message {
+                message: pb::Message::default(),
+                enqueued_at_ms: 0,
+                publish_qos: pb::QosLevel::AtMostOnce,
+            };
+            sender.send(delivery).await.unwrap();
+        }
+
+        let mut sequences = Vec::new();
+        for _ in 0..DELIVERY_COUNT {
+            let frame = framed_read.next().await.unwrap().unwrap();
+            let ClientFrame::Message(message) = frame else { panic!("expected Message frame") };
+            sequences.push(message.sequence);
+        }
+        assert_eq!(sequences, [Some(1), Some(2)]);
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_stops_subscription_when_countdown_runs_out() {
+        const SUBSCRIPTION_ID: u32 = 3;
+        let delivery = Arc::new(DeliveryControls::default());
+        delivery.subscribe(SUBSCRIPTION_ID, Some(1), pb::QosLevel::AtMostOnce);
+        let (sender, mut framed_read, _) = spawn_writer(writer_limits(delivery));
+
+        for _ in 0..2 {
+            let message = pb::Message { subscription_id: SUBSCRIPTION_ID, ..Default::default() };
+            sender
+                .send(OutboundMessage::Message {
+                    message,
+                    enqueued_at_ms: 0,
+                    publish_qos: pb::QosLevel::AtMostOnce,
+                })
+                .await
+                .unwrap();
+        }
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+
+        let first = framed_read.next().await.unwrap().unwrap();
+        let second = framed_read.next().await.unwrap().unwrap();
+        assert!(matches!(first, ClientFrame::Message(_)));
+        assert_eq!(second, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_holds_message_until_flow_credit_arrives() {
+        const SUBSCRIPTION_ID: u32 = 5;
+        let delivery = Arc::new(DeliveryControls::default());
+        delivery.subscribe(SUBSCRIPTION_ID, None, pb::QosLevel::AtMostOnce);
+        delivery.credits.grant(SUBSCRIPTION_ID, 0);
+        let (sender, mut framed_read, _) = spawn_writer(writer_limits(Arc::clone(&delivery)));
+
+        let message = pb::Message { subscription_id: SUBSCRIPTION_ID, ..Default::default() };
+        sender
+            .send(OutboundMessage::Message {
+                message,
+                enqueued_at_ms: 0,
+                publish_qos: pb::QosLevel::AtMostOnce,
+            })
+            .await
+            .unwrap();
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        let first = framed_read.next().await.unwrap().unwrap();
+        delivery.credits.grant(SUBSCRIPTION_ID, 1);
+        let granted = OutboundMessage::CreditsGranted { subscription_id: SUBSCRIPTION_ID };
+        sender.send(granted).await.unwrap();
+
+        let second = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(first, ClientFrame::Pong(pb::Pong {}));
+        assert!(matches!(second, ClientFrame::Message(_)));
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_does_not_hold_for_ended_subscription() {
+        const SUBSCRIPTION_ID: u32 = 5;
+        let delivery = Arc::new(DeliveryControls::default());
+        delivery.subscribe(SUBSCRIPTION_ID, None, pb::QosLevel::AtMostOnce);
+        delivery.credits.grant(SUBSCRIPTION_ID, 0);
+        let limits = writer_limits(Arc::clone(&delivery));
+        let services = SessionServices::new(Arc::new(NoAuthAuthenticator));
+        let mut state = WriterState::new(&limits);
+        let mut framed_write = FramedWrite::new(tokio::io::sink(), ServerCodec::default());
+        let message = || OutboundMessage::Message {
+            message: pb::Message { subscription_id: SUBSCRIPTION_ID, ..Default::default() },
+            enqueued_at_ms: 0,
+            publish_qos: pb::QosLevel::AtMostOnce,
+        };
+        dispatch_outbound(&mut framed_write, message(), 0, &limits, &services, &mut state)
+            .await
+            .unwrap();
+        delivery.unsubscribe(SUBSCRIPTION_ID, None);
+
+        dispatch_outbound(&mut framed_write, message(), 0, &limits, &services, &mut state)
+            .await
+            .unwrap();
+
+        assert_eq!(state.paused.held_count(SUBSCRIPTION_ID), 1);
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_does_not_release_stale_messages_to_a_reused_subscription_id() {
+        const SUBSCRIPTION_ID: u32 = 5;
+        let delivery = Arc::new(DeliveryControls::default());
+        delivery.subscribe(SUBSCRIPTION_ID, None, pb::QosLevel::AtMostOnce);
+        delivery.credits.grant(SUBSCRIPTION_ID, 0);
+        let (sender, mut framed_read, _) = spawn_writer(writer_limits(Arc::clone(&delivery)));
+        let message = pb::Message { subscription_id: SUBSCRIPTION_ID, ..Default::default() };
+        sender
+            .send(OutboundMessage::Message {
+                message,
+                enqueued_at_ms: 0,
+                publish_qos: pb::QosLevel::AtMostOnce,
+            })
+            .await
+            .unwrap();
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+        delivery.unsubscribe(SUBSCRIPTION_ID, None);
+        let closed = OutboundMessage::SubscriptionClosed { subscription_id: SUBSCRIPTION_ID };
+        sender.send(closed).await.unwrap();
+        delivery.subscribe(SUBSCRIPTION_ID, None, pb::QosLevel::AtMostOnce);
+        delivery.credits.grant(SUBSCRIPTION_ID, 1);
+        let granted = OutboundMessage::CreditsGranted { subscription_id: SUBSCRIPTION_ID };
+        sender.send(granted).await.unwrap();
+
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(frame, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    /// Delivery controls with `subscription_id` subscribed at `qos`, unlimited.
+    fn subscribed_with_qos(subscription_id: u32, qos: pb::QosLevel) -> Arc<DeliveryControls> {
+        let delivery = DeliveryControls::default();
+        delivery.subscribe(subscription_id, None, qos);
+        Arc::new(delivery)
+    }
+
+    fn at_least_once_delivery(subscription_id: u32) -> OutboundMessage {
+        OutboundMessage::Message {
+            message: pb::Message { subscription_id, ..Default::default() },
+            enqueued_at_ms: 0,
+            publish_qos: pb::QosLevel::AtLeastOnce,
+        }
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_redelivers_unacknowledged_at_least_once_message() {
+        const SUBSCRIPTION_ID: u32 = 2;
+        let (sender, mut framed_read, clock) = spawn_writer(writer_limits(subscribed_with_qos(
+            SUBSCRIPTION_ID,
+            pb::QosLevel::AtLeastOnce,
+        )));
+        sender.send(at_least_once_delivery(SUBSCRIPTION_ID)).await.unwrap();
+        let ClientFrame::Message(first) = framed_read.next().await.unwrap().unwrap() else {
+            panic!("expected Message frame")
+        };
+
+        clock.advance(REDELIVERY_TIMEOUT_MS);
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+
+        let ClientFrame::Message(redelivered) = framed_read.next().await.unwrap().unwrap() else {
+            panic!("expected Message frame")
+        };
+        assert!(first.delivery_id.is_some());
+        assert_eq!(redelivered.delivery_id, first.delivery_id);
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_never_redelivers_to_at_most_once_subscription() {
+        const SUBSCRIPTION_ID: u32 = 2;
+        let (sender, mut framed_read, clock) = spawn_writer(writer_limits(subscribed_with_qos(
+            SUBSCRIPTION_ID,
+            pb::QosLevel::AtMostOnce,
+        )));
+        sender.send(at_least_once_delivery(SUBSCRIPTION_ID)).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+
+        clock.advance(REDELIVERY_TIMEOUT_MS);
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(frame, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_does_not_redeliver_acknowledged_message() {
+        const SUBSCRIPTION_ID: u32 = 2;
+        let (sender, mut framed_read, clock) = spawn_writer(writer_limits(subscribed_with_qos(
+            SUBSCRIPTION_ID,
+            pb::QosLevel::AtLeastOnce,
+        )));
+        sender.send(at_least_once_delivery(SUBSCRIPTION_ID)).await.unwrap();
+        let ClientFrame::Message(message) = framed_read.next().await.unwrap().unwrap() else {
+            panic!("expected Message frame")
+        };
+        let delivery_id = message.delivery_id.unwrap();
+        sender.send(OutboundMessage::DeliveryAcknowledged { delivery_id }).await.unwrap();
+
+        clock.advance(REDELIVERY_TIMEOUT_MS);
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(frame, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    #[tokio::test]
+    async fn outbound_writer_does_not_redeliver_after_unsubscribe() {
+        const SUBSCRIPTION_ID: u32 = 2;
+        let delivery = subscribed_with_qos(SUBSCRIPTION_ID, pb::QosLevel::AtLeastOnce);
+        let (sender, mut framed_read, clock) = spawn_writer(writer_limits(Arc::clone(&delivery)));
+        sender.send(at_least_once_delivery(SUBSCRIPTION_ID)).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+        delivery.unsubscribe(SUBSCRIPTION_ID, None);
+        let closed = OutboundMessage::SubscriptionClosed { subscription_id: SUBSCRIPTION_ID };
+        sender.send(closed).await.unwrap();
+
+        clock.advance(REDELIVERY_TIMEOUT_MS);
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+        framed_read.next().await.unwrap().unwrap();
+        sender.send(OutboundMessage::Pong(pb::Pong {})).await.unwrap();
+
+        let frame = framed_read.next().await.unwrap().unwrap();
+        assert_eq!(frame, ClientFrame::Pong(pb::Pong {}));
+    }
+
+    #[tokio::test]
+    async fn client_run_fails_with_buffer_exhausted_when_partial_frames_exceed_budget() {
+        const READ_BUDGET: usize = 256;
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_rx, server_tx) = tokio::io::split(server_io);
+        let (_client_rx, client_tx) = tokio::io::split(client_io);
+
+        let transport = DuplexTransport { reader: server_rx, writer: server_tx };
+        let client = Client::with_services(
+            transport,
+            SessionServices::new(Arc::new(NoAuthAuthenticator)),
+            Arc::new(ServerConfig::new()),
+            ReadBudget::new(READ_BUDGET),
+        );
+        let server = tokio::spawn(client.run());
+
+        let mut framed_write = FramedWrite::new(client_tx, ClientCodec::default());
+        framed_write.send(ClientOutbound::connect(1, false)).await.unwrap();
+
+        let mut partial_frame = Vec::new();
+        partial_frame.put_u8(Command::Publish as u8);
+        partial_frame.put_u32(4 * READ_BUDGET as u32);
+        partial_frame.put_bytes(0, 2 * READ_BUDGET);
+        framed_write.get_mut().write_all(&partial_frame).await.unwrap();
+
+        let error = server.await.unwrap().unwrap_err();
+        assert_eq!(error.error_code(), ErrorCode::BufferExhausted);
+    }
+
+    #[test]
+    fn handshake_error_converts_and_keeps_its_error_code() {
+        let error = ClientError::from(HandshakeError::AuthenticationFailed {
+            reason: "bad credentials".to_string(),
+        });
+        assert!(matches!(error, ClientError::Handshake(_)));
+        assert_eq!(error.error_code(), ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn decode_error_maps_to_protocol_violation() {
+        let decode_error = pb::Connect::decode(&[0xFF][..]).unwrap_err();
+        let error = ClientError::from(CodecError::from(decode_error));
+        assert!(matches!(
+            error,
+            ClientError::Codec(ServerCodecError::Codec(CodecError::Decode(_)))
+        ));
+        assert_eq!(error.error_code(), ErrorCode::ProtocolViolation);
+    }
+
+    #[test]
+    fn encode_error_maps_to_internal_error() {
+        let info = pb::Info { server_id: "server".to_string(), ..Default::default() };
+        let encode_error = info.encode(&mut &mut [0u8; 0][..]).unwrap_err();
+        let error = ClientError::from(CodecError::from(encode_error));
+        assert!(matches!(
+            error,
+            ClientError::Codec(ServerCodecError::Codec(CodecError::Encode(_)))
+        ));
+        assert_eq!(error.error_code(), ErrorCode::InternalError);
+    }
+
+    #[test]
+    fn oversized_frame_maps_to_payload_too_large() {
+        let error = ClientError::from(CodecError::FrameTooLarge {
+            payload_length: MAXIMUM_PAYLOAD_BYTES + 1,
+            max_payload: MAXIMUM_PAYLOAD_BYTES,
+        });
+        assert_eq!(error.error_code(), ErrorCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn topic_error_maps_to_invalid_topic() {
+        let error = ClientError::from(TopicError::EmptyLayer);
+        assert!(matches!(error, ClientError::Topic(TopicError::EmptyLayer)));
+        assert_eq!(error.error_code(), ErrorCode::InvalidTopic);
+    }
+
+    #[test]
+    fn transport_error_maps_to_internal_error() {
+        let error = ClientError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(matches!(error, ClientError::Codec(ServerCodecError::Io(_))));
+        assert_eq!(error.error_code(), ErrorCode::InternalError);
+    }
 }
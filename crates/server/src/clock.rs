@@ -0,0 +1,60 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Source of wall-clock time for time-dependent features (TTL, keepalive, leases).
+/// Injected into the session so time-based logic can be driven deterministically in tests.
+pub trait Clock: Send + Sync + 'static {
+    /// Milliseconds since the Unix epoch.
+    fn now_ms(&self) -> u64;
+}
+
+/// Clock backed by the operating system's wall clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+/// Manually driven clock. Time only moves when `advance` or `set` is called.
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct MockClock {
+    now_ms: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl MockClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self { now_ms: AtomicU64::new(now_ms) }
+    }
+
+    pub fn advance(&self, elapsed_ms: u64) {
+        self.now_ms.fetch_add(elapsed_ms, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, now_ms: u64) {
+        self.now_ms.store(now_ms, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock_advance_moves_time_forward() {
+        let clock = MockClock::new(1_000);
+        clock.advance(250);
+        assert_eq!(clock.now_ms(), 1_250);
+    }
+}
@@ -6,6 +6,8 @@ use std::{
 
 use tracing::level_filters::LevelFilter;
 
+use crate::{headers::MAX_HEADER_BLOCK_BYTES, parser::MAXIMUM_FRAME_PAYLOAD_BYTES};
+
 // ── ServerConfig global defaults ─────────────────────────────────────────────
 const SERVER_ID: &str = "ocypode-server";
 const SERVER_NAME: &str = "ocypode";
@@ -17,6 +19,17 @@ const QUIC_READ_BUFFER_SIZE: usize = 32 * 1024;
 // 10 MiB
 const QUIC_WRITE_BUFFER_SIZE: usize = 10 * 1024 * 1024;
 const QUIC_OUTBOUND_CHANNEL_CAPACITY: usize = 1024;
+const QUIC_MAX_PAUSED_DELIVERIES: usize = 1024;
+const QUIC_REDELIVERY_TIMEOUT_MS: u64 = 30_000;
+const QUIC_MAX_INFLIGHT_DELIVERIES: usize = 1024;
+const QUIC_DEDUP_MAX_ENTRIES: usize = 100_000;
+// 16 MiB
+const QUIC_CONNECTION_READ_BUDGET: usize = 16 * 1024 * 1024;
+const QUIC_MAX_KEEPALIVE_SECS: u32 = 60;
+const QUIC_TOPIC_ALIAS_MAXIMUM: u32 = 64;
+const QUIC_MAX_REASSEMBLED_PAYLOAD: u32 = 16 * 1024 * 1024;
+const QUIC_MAX_HEADER_BYTES: u32 = MAX_HEADER_BLOCK_BYTES as u32;
+const QUIC_MAX_FRAME_PAYLOAD: usize = MAXIMUM_FRAME_PAYLOAD_BYTES;
 
 /// Ocypode server configuration.
 pub struct ServerConfig {
@@ -33,6 +46,11 @@ pub struct ServerConfig {
     /// When true, the server requires clients to present a TLS certificate (mTLS).
     /// This setting is also reflected in the INFO message sent to clients.
     pub tls_verify: bool,
+    /// Peer broker addresses ("host:port") advertised in INFO for client-side failover.
+    pub connect_urls: Vec<String>,
+    /// When set, every frame sent or received is appended to this file for offline inspection.
+    #[cfg(feature = "trace")]
+    pub trace_file_path: Option<String>,
 }
 
 impl Default for ServerConfig {
@@ -53,6 +71,9 @@ impl ServerConfig {
             server_name: SERVER_NAME.to_string(),
             requires_auth: false,
             tls_verify: false,
+            connect_urls: Vec::new(),
+            #[cfg(feature = "trace")]
+            trace_file_path: None,
         }
     }
 }
@@ -129,6 +150,38 @@ pub struct QuicConfig {
     /// Capacity of the per-client outbound mpsc channel.
     /// Higher values allow more messages to be queued before the writer task applies backpressure.
     pub outbound_channel_capacity: usize,
+    /// Maximum time in milliseconds a delivery may wait in the outbound channel.
+    /// Expired deliveries are dropped by the writer task. `None` disables expiry.
+    pub outbound_message_ttl_ms: Option<u64>,
+    /// Most deliveries held per subscription while it is out of FLOW credit.
+    /// Further deliveries to that subscription are dropped until credit arrives.
+    pub max_paused_deliveries: usize,
+    /// How long in milliseconds an at-least-once delivery may go unacknowledged before it is
+    /// sent again, up to `delivery::MAX_DELIVERY_ATTEMPTS` sends in all.
+    pub redelivery_timeout_ms: u64,
+    /// Most at-least-once deliveries awaiting their MessageAck per connection.
+    /// Further at-least-once deliveries are dropped until acks arrive.
+    pub max_inflight_deliveries: usize,
+    /// How long in milliseconds a Publish's `Ocypode-Msg-Id` is remembered per topic; a repeat
+    /// within the window is acknowledged but not delivered. `None` disables deduplication.
+    pub dedup_window_ms: Option<u64>,
+    /// Most message ids remembered across the server; the oldest are forgotten first.
+    pub dedup_max_entries: usize,
+    /// Maximum bytes of partially received frames buffered across all streams of a connection.
+    /// Complements the per-frame payload limit against many concurrent partial frames.
+    pub connection_read_budget: usize,
+    /// Longest keepalive interval in seconds a client may request, advertised in INFO.
+    /// Larger requests are capped to this value; 0 disables the idle timeout.
+    pub max_keepalive_secs: u32,
+    /// Highest topic alias a client may bind per connection, advertised in INFO. 0 disables aliases.
+    pub topic_alias_maximum: u32,
+    /// Largest payload reassembled from fragmented Publishes, advertised in INFO. 0 refuses them.
+    pub max_reassembled_payload: u32,
+    /// Largest Publish header block accepted, advertised in INFO.
+    pub max_header_bytes: u32,
+    /// Largest payload length an inbound frame header may declare. Frames above it are
+    /// rejected before buffering; keep it above `max_payload` plus the protobuf envelope.
+    pub max_frame_payload: usize,
     // QUIC requires TLS to be enabled.
     pub tls: TLSConfig,
 }
@@ -144,6 +197,18 @@ impl Default for QuicConfig {
             read_buffer_size: QUIC_READ_BUFFER_SIZE,
             write_buffer_size: QUIC_WRITE_BUFFER_SIZE,
             outbound_channel_capacity: QUIC_OUTBOUND_CHANNEL_CAPACITY,
+            outbound_message_ttl_ms: None,
+            max_paused_deliveries: QUIC_MAX_PAUSED_DELIVERIES,
+            redelivery_timeout_ms: QUIC_REDELIVERY_TIMEOUT_MS,
+            max_inflight_deliveries: QUIC_MAX_INFLIGHT_DELIVERIES,
+            dedup_window_ms: None,
+            dedup_max_entries: QUIC_DEDUP_MAX_ENTRIES,
+            connection_read_budget: QUIC_CONNECTION_READ_BUDGET,
+            max_keepalive_secs: QUIC_MAX_KEEPALIVE_SECS,
+            topic_alias_maximum: QUIC_TOPIC_ALIAS_MAXIMUM,
+            max_reassembled_payload: QUIC_MAX_REASSEMBLED_PAYLOAD,
+            max_header_bytes: QUIC_MAX_HEADER_BYTES,
+            max_frame_payload: QUIC_MAX_FRAME_PAYLOAD,
             tls: TLSConfig::default(),
         }
     }
@@ -0,0 +1,143 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use bytes::Bytes;
+
+use crate::{headers::Headers, parser::pb};
+
+/// Record of recently seen `Ocypode-Msg-Id` values, so a producer retrying a Publish within
+/// `window_ms` is not delivered twice, even across a reconnect. Ids are scoped to the Publish
+/// topic, so a client can only collide with ids on topics it is allowed to publish to; callers
+/// check permissions before asking. At most `max_entries` are remembered; the oldest are
+/// forgotten first.
+pub struct DedupWindow {
+    window_ms: u64,
+    max_entries: usize,
+    seen: Mutex<SeenIds>,
+}
+
+/// Publish topic and message id.
+type DedupKey = (Bytes, Bytes);
+
+#[derive(Default)]
+struct SeenIds {
+    first_seen_ms: HashMap<DedupKey, u64>,
+    /// Keys in the order they were first seen, for expiry and the entry cap.
+    order: VecDeque<(DedupKey, u64)>,
+}
+
+impl DedupWindow {
+    pub fn new(window_ms: u64, max_entries: usize) -> Self {
+        Self { window_ms, max_entries, seen: Mutex::default() }
+    }
+
+    /// Records the Publish's message id and returns true if the same id was already seen on
+    /// the same topic within the window. Publishes without an id, or with an unreadable header block, are
+    /// never duplicates.
+    pub fn is_duplicate(&self, publish: &pb::Publish, now_ms: u64) -> bool {
+        if publish.header.is_empty() {
+            return false;
+        }
+        let Ok(headers) = Headers::decode_from(&mut publish.header.clone()) else { return false };
+        let Some(message_id) = headers.message_id() else { return false };
+        let key = (publish.topic.clone(), message_id.clone());
+
+        let mut seen = self.seen.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.expire(&mut seen, now_ms);
+        if seen.first_seen_ms.contains_key(&key) {
+            return true;
+        }
+        seen.first_seen_ms.insert(key.clone(), now_ms);
+        seen.order.push_back((key, now_ms));
+        if seen.order.len() > self.max_entries
+            && let Some((oldest, _)) = seen.order.pop_front()
+        {
+            seen.first_seen_ms.remove(&oldest);
+        }
+        false
+    }
+
+    fn expire(&self, seen: &mut SeenIds, now_ms: u64) {
+        while let Some((_, first_seen_ms)) = seen.order.front() {
+            if now_ms.saturating_sub(*first_seen_ms) < self.window_ms {
+                break;
+            }
+            if let Some((expired, _)) = seen.order.pop_front() {
+                seen.first_seen_ms.remove(&expired);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+
+    const WINDOW_MS: u64 = 5_000;
+    const MAX_ENTRIES: usize = 2;
+    const TOPIC: &[u8] = b"a/b";
+
+    fn publish(message_id: Option<&str>) -> pb::Publish {
+        publish_to(TOPIC, message_id)
+    }
+
+    fn publish_to(topic: &'static [u8], message_id: Option<&str>) -> pb::Publish {
+        let mut headers = Headers::new();
+        if let Some(message_id) = message_id {
+            headers.insert_message_id(message_id.to_string()).unwrap();
+        }
+        let mut header = BytesMut::new();
+        headers.encode_to(&mut header).unwrap();
+        pb::Publish {
+            topic: Bytes::from_static(topic),
+            header: header.freeze(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn repeated_message_id_within_window_is_duplicate() {
+        let window = DedupWindow::new(WINDOW_MS, MAX_ENTRIES);
+        window.is_duplicate(&publish(Some("a")), 0);
+
+        assert!(window.is_duplicate(&publish(Some("a")), WINDOW_MS - 1));
+    }
+
+    #[test]
+    fn repeated_message_id_after_window_is_delivered() {
+        let window = DedupWindow::new(WINDOW_MS, MAX_ENTRIES);
+        window.is_duplicate(&publish(Some("a")), 0);
+
+        assert!(!window.is_duplicate(&publish(Some("a")), WINDOW_MS));
+    }
+
+    #[test]
+    fn same_message_id_on_other_topic_is_delivered() {
+        let window = DedupWindow::new(WINDOW_MS, MAX_ENTRIES);
+        window.is_duplicate(&publish_to(b"a/b", Some("a")), 0);
+
+        assert!(!window.is_duplicate(&publish_to(b"c/d", Some("a")), 0));
+    }
+
+    #[test]
+    fn publish_without_message_id_is_never_duplicate() {
+        let window = DedupWindow::new(WINDOW_MS, MAX_ENTRIES);
+        window.is_duplicate(&publish(None), 0);
+
+        assert!(!window.is_duplicate(&publish(None), 0));
+    }
+
+    #[test]
+    fn oldest_id_is_forgotten_beyond_max_entries() {
+        let window = DedupWindow::new(WINDOW_MS, MAX_ENTRIES);
+        for message_id in ["a", "b", "c"] {
+            window.is_duplicate(&publish(Some(message_id)), 0);
+        }
+
+        assert!(!window.is_duplicate(&publish(Some("a")), 0));
+    }
+}
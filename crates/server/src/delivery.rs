@@ -0,0 +1,592 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::parser::pb;
+
+/// Identifier the server assigns to a tracked delivery, echoed back by the subscriber's ack.
+pub type DeliveryId = u64;
+
+/// Strongest guarantee the broker implements. The reserved `ExactlyOnce` is served as this.
+pub const MAX_SUPPORTED_QOS: pb::QosLevel = pb::QosLevel::AtLeastOnce;
+
+/// The guarantee a delivery actually gets: the weaker of what the publisher asked for and
+/// what the subscription accepts, capped at `MAX_SUPPORTED_QOS`.
+pub fn effective_qos(publish: pb::QosLevel, subscription: pb::QosLevel) -> pb::QosLevel {
+    publish.min(subscription).min(MAX_SUPPORTED_QOS)
+}
+
+/// Sends of one at-least-once delivery, the first included, before the writer gives up on
+/// its ack and frees its slot.
+pub const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+struct InflightDelivery {
+    message: pb::Message,
+    sent_at_ms: u64,
+    attempts: u32,
+}
+
+/// Tracks at-least-once deliveries awaiting acknowledgement so overdue ones can be redelivered.
+/// At-most-once deliveries bypass the tracker entirely. Owned by the connection's writer.
+pub struct InflightTracker {
+    redelivery_timeout_ms: u64,
+    max_inflight: usize,
+    next_delivery_id: DeliveryId,
+    inflight: HashMap<DeliveryId, InflightDelivery>,
+}
+
+impl InflightTracker {
+    pub fn new(redelivery_timeout_ms: u64, max_inflight: usize) -> Self {
+        Self { redelivery_timeout_ms, max_inflight, next_delivery_id: 0, inflight: HashMap::new() }
+    }
+
+    /// Records a delivery according to its `effective_qos`, stamping `message` with the id to
+    /// acknowledge when it is tracked. Returns false, leaving `message` untouched, when
+    /// `max_inflight` deliveries already await their ack; the caller drops it as it would for
+    /// any other slow consumer. The reserved `ExactlyOnce` is tracked as `AtLeastOnce`.
+    pub fn on_deliver(
+        &mut self,
+        qos: pb::QosLevel,
+        message: &mut pb::Message,
+        now_ms: u64,
+    ) -> bool {
+        match qos {
+            pb::QosLevel::AtMostOnce => {
+                message.delivery_id = None;
+                true
+            }
+            pb::QosLevel::AtLeastOnce | pb::QosLevel::ExactlyOnce => {
+                if self.inflight.len() >= self.max_inflight {
+                    return false;
+                }
+                let delivery_id = self.next_delivery_id;
+                self.next_delivery_id += 1;
+                message.delivery_id = Some(delivery_id);
+                self.inflight.insert(
+                    delivery_id,
+                    InflightDelivery { message: message.clone(), sent_at_ms: now_ms, attempts: 1 },
+                );
+                true
+            }
+        }
+    }
+
+    /// Clears a tracked delivery. Returns false when the id is unknown or already acknowledged.
+    pub fn ack(&mut self, delivery_id: DeliveryId) -> bool {
+        self.inflight.remove(&delivery_id).is_some()
+    }
+
+    /// Forgets every delivery made to a subscription that has ended.
+    pub fn forget_subscription(&mut self, subscription_id: u32) {
+        self.inflight.retain(|_, delivery| delivery.message.subscription_id != subscription_id);
+    }
+
+    /// Returns deliveries whose ack is overdue, oldest first, and restarts their redelivery
+    /// timers. Each keeps its `delivery_id`. Deliveries to subscriptions `is_open` rejects, and
+    /// overdue ones already sent `MAX_DELIVERY_ATTEMPTS` times, are forgotten instead.
+    pub fn take_redeliveries(
+        &mut self,
+        now_ms: u64,
+        is_open: impl Fn(u32) -> bool,
+    ) -> Vec<pb::Message> {
+        let redelivery_timeout_ms = self.redelivery_timeout_ms;
+        let is_overdue = |delivery: &InflightDelivery| {
+            now_ms.saturating_sub(delivery.sent_at_ms) >= redelivery_timeout_ms
+        };
+        self.inflight.retain(|_, delivery| {
+            is_open(delivery.message.subscription_id)
+                && !(is_overdue(delivery) && delivery.attempts >= MAX_DELIVERY_ATTEMPTS)
+        });
+        let mut overdue: Vec<_> = self
+            .inflight
+            .iter_mut()
+            .filter(|(_, delivery)| is_overdue(delivery))
+            .map(|(delivery_id, delivery)| {
+                delivery.sent_at_ms = now_ms;
+                delivery.attempts += 1;
+                (*delivery_id, delivery.message.clone())
+            })
+            .collect();
+        overdue.sort_unstable_by_key(|(delivery_id, _)| *delivery_id);
+        overdue.into_iter().map(|(_, message)| message).collect()
+    }
+}
+
+/// Auto-unsubscribe countdowns for a connection's active subscriptions, shared by its reader
+/// and writer. There is an entry exactly while the subscription is active: `None` for no limit,
+/// or the deliveries left. The writer consumes one per delivery as it hits the wire, and the
+/// entry is removed when it reaches zero. Deliveries already queued when a countdown was set
+/// are counted too, so exactly `max_msgs` more messages go out.
+#[derive(Default)]
+pub struct DeliveryCountdowns {
+    remaining: Mutex<HashMap<u32, Option<u64>>>,
+}
+
+impl DeliveryCountdowns {
+    /// Activates the subscription, allowing `max_msgs` deliveries; `None` means no limit.
+    pub fn open(&self, subscription_id: u32, max_msgs: Option<u64>) {
+        let mut remaining = self.remaining.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        remaining.insert(subscription_id, max_msgs);
+    }
+
+    /// Allows `max_msgs` more deliveries for an active subscription. Returns false, changing
+    /// nothing, when the subscription is not active.
+    pub fn limit(&self, subscription_id: u32, max_msgs: u64) -> bool {
+        let mut remaining = self.remaining.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(countdown) = remaining.get_mut(&subscription_id) else { return false };
+        *countdown = Some(max_msgs);
+        true
+    }
+
+    /// Ends the subscription; deliveries still queued for it are dropped. Returns false when
+    /// it was not active.
+    pub fn close(&self, subscription_id: u32) -> bool {
+        let mut remaining = self.remaining.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        remaining.remove(&subscription_id).is_some()
+    }
+}
+
+/// Flow control credits per subscription id, granted by the reader on FLOW and spent by the
+/// writer. Only subscriptions opened on Subscribe can be granted credit, so FLOW naming an
+/// unknown id cannot grow the table. An open subscription that never sent FLOW is not flow
+/// controlled.
+#[derive(Default)]
+pub struct DeliveryCredits {
+    /// `None` until the subscription's first FLOW.
+    balance: Mutex<HashMap<u32, Option<u64>>>,
+}
+
+impl DeliveryCredits {
+    /// Makes the subscription eligible for FLOW; called when its Subscribe is accepted.
+    pub fn open(&self, subscription_id: u32) {
+        let mut balance = self.balance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        balance.entry(subscription_id).or_insert(None);
+    }
+
+    /// Forgets the subscription's balance once it is unsubscribed.
+    pub fn close(&self, subscription_id: u32) {
+        let mut balance = self.balance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        balance.remove(&subscription_id);
+    }
+
+    /// Adds `credits` to the subscription's balance, putting it under flow control.
+    /// Returns false, granting nothing, when the subscription is not open.
+    pub fn grant(&self, subscription_id: u32, credits: u32) -> bool {
+        let mut balance = self.balance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(entry) = balance.get_mut(&subscription_id) else { return false };
+        *entry = Some(entry.unwrap_or_default().saturating_add(u64::from(credits)));
+        true
+    }
+
+    /// Spends one credit. Returns false when a flow-controlled subscription has none left.
+    pub fn try_consume(&self, subscription_id: u32) -> bool {
+        let mut balance = self.balance.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match balance.get_mut(&subscription_id) {
+            Some(Some(0)) => false,
+            Some(Some(credits)) => {
+                *credits -= 1;
+                true
+            }
+            Some(None) | None => true,
+        }
+    }
+}
+
+/// What the writer does with a Message, decided by `DeliveryControls::try_admit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Write it now with at most the subscription's `qos`; the countdown and any credit have
+    /// been spent.
+    Deliver { qos: pb::QosLevel },
+    /// Hold it until FLOW grants more credit.
+    NoCredit,
+    /// Drop it; the subscription has ended or its auto-unsubscribe countdown has run out.
+    Exhausted,
+}
+
+/// Per-connection delivery state shared by the reader, which updates it from Subscribe,
+/// UnSubscribe and Flow, and the writer, which consults it for every Message.
+#[derive(Default)]
+pub struct DeliveryControls {
+    pub countdowns: DeliveryCountdowns,
+    pub credits: DeliveryCredits,
+    /// The QoS each active subscription asked for in its Subscribe.
+    qos: Mutex<HashMap<u32, pb::QosLevel>>,
+}
+
+impl DeliveryControls {
+    /// Starts tracking a subscription whose Subscribe was accepted.
+    pub fn subscribe(&self, subscription_id: u32, max_msgs: Option<u64>, qos: pb::QosLevel) {
+        if max_msgs == Some(0) {
+            self.unsubscribe(subscription_id, None);
+            return;
+        }
+        // The QoS goes in first so the writer never admits a delivery without it.
+        self.qos
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(subscription_id, qos);
+        self.countdowns.open(subscription_id, max_msgs);
+        self.credits.open(subscription_id);
+    }
+
+    /// Ends the subscription now, or after `max_msgs` more deliveries. Returns true when an
+    /// active subscription ended now.
+    pub fn unsubscribe(&self, subscription_id: u32, max_msgs: Option<u64>) -> bool {
+        match max_msgs {
+            Some(max_msgs) if max_msgs > 0 => {
+                self.countdowns.limit(subscription_id, max_msgs);
+                false
+            }
+            _ => self.close(subscription_id),
+        }
+    }
+
+    /// True while the subscription is active.
+    pub fn is_open(&self, subscription_id: u32) -> bool {
+        self.countdowns
+            .remaining
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains_key(&subscription_id)
+    }
+
+    fn close(&self, subscription_id: u32) -> bool {
+        self.credits.close(subscription_id);
+        self.qos.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&subscription_id);
+        self.countdowns.close(subscription_id)
+    }
+
+    /// Admits one delivery. The countdown is checked before a credit is spent, so a message
+    /// dropped by the countdown never costs one; both are then spent together. The last
+    /// delivery a countdown allows ends the subscription.
+    pub fn try_admit(&self, subscription_id: u32) -> Admission {
+        let mut remaining =
+            self.countdowns.remaining.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(countdown) = remaining.get_mut(&subscription_id) else {
+            return Admission::Exhausted;
+        };
+        if !self.credits.try_consume(subscription_id) {
+            return Admission::NoCredit;
+        }
+        let qos = self
+            .qos
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&subscription_id)
+            .copied()
+            .unwrap_or_default();
+        if let Some(count) = countdown {
+            *count -= 1;
+            if *count == 0 {
+                remaining.remove(&subscription_id);
+                drop(remaining);
+                self.close(subscription_id);
+            }
+        }
+        Admission::Deliver { qos }
+    }
+}
+
+/// Messages the writer holds back while their subscription is out of credit, in delivery
+/// order, each with the QoS its publisher asked for. Each subscription holds at most
+/// `max_held`; further messages are dropped, as for any other slow consumer.
+pub struct PausedDeliveries {
+    max_held: usize,
+    held: HashMap<u32, VecDeque<(pb::Message, pb::QosLevel)>>,
+}
+
+impl PausedDeliveries {
+    pub fn new(max_held: usize) -> Self {
+        Self { max_held, held: HashMap::new() }
+    }
+
+    /// True while the subscription has held messages; new ones must queue behind them.
+    pub fn is_paused(&self, subscription_id: u32) -> bool {
+        self.held.contains_key(&subscription_id)
+    }
+
+    /// Holds `message` until credit arrives. Returns false if it was dropped instead. The
+    /// caller only holds for an active subscription, so an ended one cannot pin messages.
+    pub fn hold(&mut self, message: pb::Message, publish_qos: pb::QosLevel) -> bool {
+        let held = self.held.entry(message.subscription_id).or_default();
+        if held.len() >= self.max_held {
+            return false;
+        }
+        held.push_back((message, publish_qos));
+        true
+    }
+
+    #[cfg(test)]
+    pub fn held_count(&self, subscription_id: u32) -> usize {
+        self.held.get(&subscription_id).map_or(0, VecDeque::len)
+    }
+
+    /// Drops everything held for a subscription that has ended, so a later subscription
+    /// reusing its id never receives them.
+    pub fn discard(&mut self, subscription_id: u32) {
+        self.held.remove(&subscription_id);
+    }
+
+    /// Takes as many held messages as `controls` now admits, oldest first, each with its
+    /// `effective_qos`. The subscription stays paused while any are left; once its countdown
+    /// runs out the rest are dropped.
+    pub fn release(
+        &mut self,
+        subscription_id: u32,
+        controls: &DeliveryControls,
+    ) -> Vec<(pb::Message, pb::QosLevel)> {
+        let Some(held) = self.held.get_mut(&subscription_id) else { return Vec::new() };
+        let mut released = Vec::new();
+        while !held.is_empty() {
+            match controls.try_admit(subscription_id) {
+                Admission::Deliver { qos } => released.extend(
+                    held.pop_front()
+                        .map(|(message, publish_qos)| (message, effective_qos(publish_qos, qos))),
+                ),
+                Admission::NoCredit => break,
+                Admission::Exhausted => held.clear(),
+            }
+        }
+        if held.is_empty() {
+            self.held.remove(&subscription_id);
+        }
+        released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REDELIVERY_TIMEOUT_MS: u64 = 1_000;
+    const MAX_INFLIGHT: usize = 4;
+    const MAX_HELD: usize = 4;
+
+    #[test]
+    fn at_most_once_delivery_is_never_redelivered() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        tracker.on_deliver(pb::QosLevel::AtMostOnce, &mut pb::Message::default(), 0);
+        assert!(tracker.take_redeliveries(10 * REDELIVERY_TIMEOUT_MS, |_| true).is_empty());
+    }
+
+    #[test]
+    fn at_least_once_delivery_is_redelivered_when_ack_is_missing() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        let mut message = pb::Message::default();
+        tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut message, 0);
+        let redeliveries = tracker.take_redeliveries(REDELIVERY_TIMEOUT_MS, |_| true);
+        assert_eq!(redeliveries, vec![message]);
+    }
+
+    #[test]
+    fn acknowledged_delivery_is_not_redelivered() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        let mut message = pb::Message::default();
+        tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut message, 0);
+        tracker.ack(message.delivery_id.unwrap());
+        assert!(tracker.take_redeliveries(REDELIVERY_TIMEOUT_MS, |_| true).is_empty());
+    }
+
+    #[test]
+    fn delivery_to_ended_subscription_is_not_redelivered() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0);
+
+        assert!(tracker.take_redeliveries(REDELIVERY_TIMEOUT_MS, |_| false).is_empty());
+    }
+
+    #[test]
+    fn redelivery_stops_after_max_attempts() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0);
+        let redelivered = (1..=u64::from(MAX_DELIVERY_ATTEMPTS))
+            .filter(|&round| {
+                !tracker.take_redeliveries(round * REDELIVERY_TIMEOUT_MS, |_| true).is_empty()
+            })
+            .count();
+
+        assert_eq!(redelivered as u32, MAX_DELIVERY_ATTEMPTS - 1);
+    }
+
+    #[test]
+    fn forgetting_a_subscription_frees_its_inflight_slots() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        for _ in 0..MAX_INFLIGHT {
+            tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0);
+        }
+
+        tracker.forget_subscription(0);
+
+        assert!(tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0));
+    }
+
+    #[test]
+    fn full_tracker_refuses_at_least_once_delivery() {
+        let mut tracker = InflightTracker::new(REDELIVERY_TIMEOUT_MS, MAX_INFLIGHT);
+        for _ in 0..MAX_INFLIGHT {
+            tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0);
+        }
+
+        assert!(!tracker.on_deliver(pb::QosLevel::AtLeastOnce, &mut pb::Message::default(), 0));
+    }
+
+    #[test]
+    fn countdown_allows_exactly_max_msgs_deliveries() {
+        const MAX_MSGS: u64 = 2;
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, Some(MAX_MSGS), pb::QosLevel::AtMostOnce);
+
+        let delivered =
+            (0..5).filter(|_| matches!(controls.try_admit(1), Admission::Deliver { .. })).count();
+
+        assert_eq!(delivered as u64, MAX_MSGS);
+    }
+
+    #[test]
+    fn effective_qos_is_the_weaker_of_publish_and_subscription() {
+        let qos = effective_qos(pb::QosLevel::AtLeastOnce, pb::QosLevel::AtMostOnce);
+
+        assert_eq!(qos, pb::QosLevel::AtMostOnce);
+    }
+
+    #[test]
+    fn exactly_once_is_downgraded_to_the_supported_maximum() {
+        let qos = effective_qos(pb::QosLevel::ExactlyOnce, pb::QosLevel::ExactlyOnce);
+
+        assert_eq!(qos, MAX_SUPPORTED_QOS);
+    }
+
+    #[test]
+    fn credits_allow_exactly_the_granted_deliveries() {
+        const GRANTED: u32 = 3;
+        let credits = DeliveryCredits::default();
+        credits.open(1);
+        credits.grant(1, GRANTED);
+
+        let delivered = (0..5).filter(|_| credits.try_consume(1)).count();
+
+        assert_eq!(delivered as u32, GRANTED);
+    }
+
+    #[test]
+    fn flow_for_unknown_subscription_grants_nothing() {
+        let credits = DeliveryCredits::default();
+
+        assert!(!credits.grant(1, 1));
+    }
+
+    #[test]
+    fn ended_subscription_does_not_spend_credit() {
+        let controls = DeliveryControls::default();
+        controls.credits.open(1);
+        controls.credits.grant(1, 1);
+
+        assert_eq!(controls.try_admit(1), Admission::Exhausted);
+        assert!(controls.credits.try_consume(1));
+    }
+
+    #[test]
+    fn countdown_entry_is_removed_when_it_runs_out() {
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, Some(1), pb::QosLevel::AtMostOnce);
+
+        controls.try_admit(1);
+
+        assert!(controls.countdowns.remaining.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unsubscribe_of_unknown_subscription_adds_no_entry() {
+        let controls = DeliveryControls::default();
+
+        controls.unsubscribe(1, Some(1));
+
+        assert!(controls.countdowns.remaining.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unsubscribe_without_max_msgs_removes_the_entry() {
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, None, pb::QosLevel::AtMostOnce);
+
+        controls.unsubscribe(1, None);
+
+        assert!(controls.countdowns.remaining.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn subscription_without_flow_is_not_flow_controlled() {
+        let credits = DeliveryCredits::default();
+
+        assert!(credits.try_consume(1));
+    }
+
+    #[test]
+    fn release_returns_held_messages_up_to_the_new_credit() {
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, None, pb::QosLevel::AtMostOnce);
+        controls.credits.grant(1, 0);
+        let mut paused = PausedDeliveries::new(MAX_HELD);
+        for sequence in 0..3 {
+            paused.hold(
+                pb::Message { subscription_id: 1, sequence: Some(sequence), ..Default::default() },
+                pb::QosLevel::AtMostOnce,
+            );
+        }
+        controls.credits.grant(1, 2);
+
+        let released = paused.release(1, &controls);
+
+        assert_eq!(
+            released.iter().map(|(message, _)| message.sequence).collect::<Vec<_>>(),
+            vec![Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn discard_frees_held_messages() {
+        let mut paused = PausedDeliveries::new(MAX_HELD);
+        paused.hold(
+            pb::Message { subscription_id: 1, ..Default::default() },
+            pb::QosLevel::AtMostOnce,
+        );
+
+        paused.discard(1);
+
+        assert!(!paused.is_paused(1));
+    }
+
+    #[test]
+    fn hold_drops_messages_beyond_the_cap() {
+        let mut paused = PausedDeliveries::new(MAX_HELD);
+        for _ in 0..MAX_HELD {
+            paused.hold(
+                pb::Message { subscription_id: 1, ..Default::default() },
+                pb::QosLevel::AtMostOnce,
+            );
+        }
+
+        assert!(!paused.hold(
+            pb::Message { subscription_id: 1, ..Default::default() },
+            pb::QosLevel::AtMostOnce
+        ));
+    }
+
+    #[test]
+    fn subscription_without_countdown_is_unlimited() {
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, None, pb::QosLevel::AtMostOnce);
+
+        assert!(matches!(controls.try_admit(1), Admission::Deliver { .. }));
+    }
+
+    #[test]
+    fn admission_carries_the_subscription_qos() {
+        let controls = DeliveryControls::default();
+        controls.subscribe(1, None, pb::QosLevel::AtLeastOnce);
+
+        assert_eq!(controls.try_admit(1), Admission::Deliver { qos: pb::QosLevel::AtLeastOnce });
+    }
+}
@@ -2,6 +2,8 @@ use std::io;
 
 use thiserror::Error;
 
+use crate::parser::{Command, MAX_PRIORITY};
+
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, Error)]
 pub enum TopicError {
@@ -9,6 +11,10 @@ pub enum TopicError {
     Empty,
     #[error("topic is too long: {len} bytes")]
     TooLong { len: usize },
+    #[error("topic is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("topic contains a NUL byte")]
+    ContainsNul,
     #[error("topic has a leading slash")]
     LeadingSlash,
     #[error("topic has a trailing slash")]
@@ -25,8 +31,138 @@ pub enum TopicError {
     WildcardInPublishTopic,
     #[error("multi-level wildcard '#' must be the last segment")]
     MultiWildcardNotTerminal,
-    #[error("wildcard characters must occupy an entire segment")]
-    InvalidWildcardUsage,
+    #[error("layer {layer_index} mixes a wildcard with other characters")]
+    InvalidWildcardLayer { layer_index: usize },
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum FragmentError {
+    #[error("fragment {received} arrived where fragment {expected} was expected")]
+    OutOfOrder { expected: u32, received: u32 },
+    #[error("unfragmented frame arrived where fragment {expected} was expected")]
+    Interrupted { expected: u32 },
+    #[error("fragment {index} changes the topic or header of fragment 0")]
+    Mismatched { index: u32 },
+    #[error("reassembled payload of {size} bytes exceeds the maximum of {max}")]
+    TooLarge { size: usize, max: usize },
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum TopicAliasError {
+    #[error("topic alias {alias} is outside 1..={maximum}")]
+    OutOfRange { alias: u32, maximum: u32 },
+    #[error("topic alias {alias} has no topic bound to it")]
+    Unbound { alias: u32 },
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum HeadersError {
+    #[error("header buffer ends before the declared entries")]
+    BufferTooShort,
+    #[error("header key is empty")]
+    EmptyKey,
+    #[error("header block declares {count} entries")]
+    TooManyHeaders { count: u64 },
+    #[error("header block is too large: {size} bytes")]
+    HeaderBlockTooLarge { size: usize },
+    #[error("header entry count uses a non-canonical varint encoding")]
+    NonCanonicalVarint,
+    #[error("header key uses the reserved Ocypode- prefix")]
+    #[allow(dead_code)]
+    ReservedKey,
+    #[error("header key or value contains a control character")]
+    ControlCharacter,
+    #[error("header key is too long: {length} bytes")]
+    KeyTooLong { length: usize },
+    #[error("header value is too long: {length} bytes")]
+    ValueTooLong { length: usize },
+}
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum RequestError {
+    #[error("message has no reply_to topic to answer")]
+    NoReplyTo,
+    #[error("invalid reply_to topic: {0}")]
+    Topic(#[from] TopicError),
+    #[error("invalid request headers: {0}")]
+    Headers(#[from] HeadersError),
+    #[error("random source unavailable: {0}")]
+    Random(io::ErrorKind),
+}
+
+/// Application error codes sent to the peer in a QUIC CONNECTION_CLOSE frame or an ERROR frame.
+/// The numeric values are part of the wire contract and must stay stable; clients match on
+/// them rather than on the reason text. `is_fatal` tells which codes always end the connection.
+#[allow(dead_code)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// 0x00: graceful close, nothing went wrong.
+    NoError = 0x00,
+    /// 0x01: a frame broke the protocol, e.g. out-of-order fragments or an undecodable payload.
+    ProtocolViolation = 0x01,
+    /// 0x02: the authenticator rejected CONNECT.
+    AuthFailed = 0x02,
+    /// 0x03: no CONNECT arrived within the connect timeout.
+    ConnectTimeout = 0x03,
+    /// 0x04: the server failed for reasons unrelated to the client's frames.
+    InternalError = 0x04,
+    /// 0x05: partially received frames exceeded the connection read budget.
+    BufferExhausted = 0x05,
+    /// 0x06: a topic or topic filter failed validation.
+    InvalidTopic = 0x06,
+    /// 0x07: a payload or frame exceeded the negotiated maximum.
+    PayloadTooLarge = 0x07,
+    /// 0x08: the client is not permitted to publish or subscribe to the topic.
+    PermissionViolation = 0x08,
+    /// 0x09: the client fell too far behind on deliveries.
+    SlowConsumer = 0x09,
+    /// 0x0A: the client stayed silent past its negotiated keepalive.
+    IdleTimeout = 0x0A,
+}
+
+impl ErrorCode {
+    /// True for codes that only ever end the connection. The others answer a single request
+    /// and leave the connection open, unless sent with `ServerOutbound::fatal_error`.
+    /// `ProtocolViolation` is not fatal: it rejects one well-framed but invalid request, such
+    /// as an out-of-order fragment. A frame that cannot be decoded still closes the connection
+    /// with `ProtocolViolation`, because the stream cannot be resynchronized after it.
+    pub fn is_fatal(self) -> bool {
+        match self {
+            ErrorCode::AuthFailed
+            | ErrorCode::ConnectTimeout
+            | ErrorCode::InternalError
+            | ErrorCode::BufferExhausted
+            | ErrorCode::SlowConsumer
+            | ErrorCode::IdleTimeout => true,
+            ErrorCode::NoError
+            | ErrorCode::ProtocolViolation
+            | ErrorCode::InvalidTopic
+            | ErrorCode::PayloadTooLarge
+            | ErrorCode::PermissionViolation => false,
+        }
+    }
+}
+
+impl TryFrom<u32> for ErrorCode {
+    type Error = CodecError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(ErrorCode::NoError),
+            0x01 => Ok(ErrorCode::ProtocolViolation),
+            0x02 => Ok(ErrorCode::AuthFailed),
+            0x03 => Ok(ErrorCode::ConnectTimeout),
+            0x04 => Ok(ErrorCode::InternalError),
+            0x05 => Ok(ErrorCode::BufferExhausted),
+            0x06 => Ok(ErrorCode::InvalidTopic),
+            0x07 => Ok(ErrorCode::PayloadTooLarge),
+            0x08 => Ok(ErrorCode::PermissionViolation),
+            0x09 => Ok(ErrorCode::SlowConsumer),
+            0x0A => Ok(ErrorCode::IdleTimeout),
+            _ => Err(CodecError::UnknownErrorCode(value)),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -41,8 +177,36 @@ pub enum CodecError {
     Encode(#[from] prost::EncodeError),
     #[error("Decode error: {0}")]
     Decode(#[from] prost::DecodeError),
+    /// `source` names the message and field that failed, e.g. `Publish.topic`; prost does not
+    /// report a byte offset.
+    #[error("Failed to decode {command:?} payload: {source}")]
+    DecodePayload {
+        command: Command,
+        #[source]
+        source: prost::DecodeError,
+    },
     #[error("Invalid size bytes: {0}")]
     InvalidSizeBytes(usize),
+    #[error("Connection read budget exhausted: {buffered} bytes buffered, budget {budget}")]
+    BufferExhausted { buffered: usize, budget: usize },
+    #[error("Payload of {size} bytes exceeds the maximum of {max}")]
+    PayloadTooLarge { size: usize, max: usize },
+    #[error("Header block of {size} bytes exceeds the maximum of {max}")]
+    HeadersTooLarge { size: usize, max: usize },
+    #[error("Frame payload of {payload_length} bytes exceeds the maximum of {max_payload}")]
+    FrameTooLarge { payload_length: usize, max_payload: usize },
+    #[error("Unknown error code: {0}")]
+    UnknownErrorCode(u32),
+    #[error("Unknown auth method: {0}")]
+    UnknownAuthMethod(i32),
+    #[error("Unknown QoS level: {0}")]
+    UnknownQos(i32),
+    #[error("Priority {0} is above the maximum of {max}", max = MAX_PRIORITY)]
+    InvalidPriority(u32),
+    #[error("Invalid queue group: {length} bytes")]
+    InvalidQueueGroup { length: usize },
+    #[error("PublishBatch of {entries} entries exceeds the maximum of {max}")]
+    TooManyBatchEntries { entries: usize, max: usize },
     #[error("Invalid version: {0}")]
     #[allow(dead_code)]
     InvalidVersion(String),
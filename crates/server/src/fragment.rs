@@ -0,0 +1,239 @@
+use bytes::{Bytes, BytesMut};
+
+pub use crate::error::FragmentError;
+use crate::parser::pb;
+
+/// A frame whose payload can be carried across several consecutive frames.
+pub trait Fragmentable: Clone {
+    fn fragment(&self) -> Option<pb::Fragment>;
+    fn set_fragment(&mut self, fragment: Option<pb::Fragment>);
+    fn payload(&self) -> &Bytes;
+    fn set_payload(&mut self, payload: Bytes);
+    fn topic(&self) -> &Bytes;
+    fn header(&self) -> &Bytes;
+}
+
+impl Fragmentable for pb::Publish {
+    fn fragment(&self) -> Option<pb::Fragment> {
+        self.fragment
+    }
+
+    fn set_fragment(&mut self, fragment: Option<pb::Fragment>) {
+        self.fragment = fragment;
+    }
+
+    fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    fn set_payload(&mut self, payload: Bytes) {
+        self.payload = payload;
+    }
+
+    fn topic(&self) -> &Bytes {
+        &self.topic
+    }
+
+    fn header(&self) -> &Bytes {
+        &self.header
+    }
+}
+
+impl Fragmentable for pb::Message {
+    fn fragment(&self) -> Option<pb::Fragment> {
+        self.fragment
+    }
+
+    fn set_fragment(&mut self, fragment: Option<pb::Fragment>) {
+        self.fragment = fragment;
+    }
+
+    fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    fn set_payload(&mut self, payload: Bytes) {
+        self.payload = payload;
+    }
+
+    fn topic(&self) -> &Bytes {
+        &self.topic
+    }
+
+    fn header(&self) -> &Bytes {
+        &self.header
+    }
+}
+
+/// Splits `item` into fragments carrying at most `fragment_bytes` of payload each.
+/// Every fragment repeats the other fields, so each one stands alone as a valid frame.
+/// A payload that already fits is returned unchanged, without a `fragment` field.
+/// Fragment payloads are slices of the original payload, not copies.
+#[allow(dead_code)]
+pub fn split<T: Fragmentable>(item: T, fragment_bytes: usize) -> Vec<T> {
+    let payload = item.payload().clone();
+    if payload.len() <= fragment_bytes {
+        return vec![item];
+    }
+    let chunk_count = payload.len().div_ceil(fragment_bytes);
+    (0..chunk_count)
+        .map(|index| {
+            let start = index * fragment_bytes;
+            let end = (start + fragment_bytes).min(payload.len());
+            let mut fragment = item.clone();
+            fragment.set_payload(payload.slice(start..end));
+            fragment.set_fragment(Some(pb::Fragment {
+                index: index as u32,
+                last: index + 1 == chunk_count,
+            }));
+            fragment
+        })
+        .collect()
+}
+
+/// Joins consecutive fragments of one stream back into a single frame.
+/// Fragments must arrive in order starting at index 0 and repeat fragment 0's topic and
+/// header; anything else discards the partial payload so the next frame starts clean.
+pub struct Reassembler<T> {
+    max_payload: usize,
+    pending: Option<T>,
+    joined: BytesMut,
+    next_index: u32,
+}
+
+impl<T: Fragmentable> Reassembler<T> {
+    /// `max_payload` caps the joined payload; 0 refuses fragmented frames entirely.
+    pub fn new(max_payload: usize) -> Self {
+        Self { max_payload, pending: None, joined: BytesMut::new(), next_index: 0 }
+    }
+
+    /// Feeds one frame. Returns the complete frame once its last fragment arrives; frames
+    /// without a `fragment` field pass straight through.
+    pub fn push(&mut self, item: T) -> Result<Option<T>, FragmentError> {
+        let Some(fragment) = item.fragment() else {
+            if self.pending.is_some() {
+                let expected = self.next_index;
+                self.reset();
+                return Err(FragmentError::Interrupted { expected });
+            }
+            return Ok(Some(item));
+        };
+        if fragment.index != self.next_index {
+            let expected = self.next_index;
+            self.reset();
+            return Err(FragmentError::OutOfOrder { expected, received: fragment.index });
+        }
+        if let Some(pending) = &self.pending
+            && (pending.topic() != item.topic() || pending.header() != item.header())
+        {
+            self.reset();
+            return Err(FragmentError::Mismatched { index: fragment.index });
+        }
+
+        let size = self.joined.len() + item.payload().len();
+        if size > self.max_payload {
+            self.reset();
+            return Err(FragmentError::TooLarge { size, max: self.max_payload });
+        }
+        self.joined.extend_from_slice(item.payload());
+        let pending = self.pending.get_or_insert(item);
+
+        if !fragment.last {
+            self.next_index += 1;
+            return Ok(None);
+        }
+        pending.set_payload(std::mem::take(&mut self.joined).freeze());
+        pending.set_fragment(None);
+        let complete = self.pending.take();
+        self.next_index = 0;
+        Ok(complete)
+    }
+
+    fn reset(&mut self) {
+        self.pending = None;
+        self.joined.clear();
+        self.next_index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAGMENT_BYTES: usize = 4;
+    const MAX_PAYLOAD: usize = 64;
+
+    fn publish(payload: &[u8]) -> pb::Publish {
+        pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            payload: Bytes::copy_from_slice(payload),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn split_fragments_reassemble_to_original() {
+        let original = publish(b"0123456789");
+        let mut reassembler = Reassembler::new(MAX_PAYLOAD);
+
+        let fragments = split(original.clone(), FRAGMENT_BYTES);
+        let joined: Vec<_> = fragments
+            .into_iter()
+            .filter_map(|fragment| reassembler.push(fragment).unwrap())
+            .collect();
+
+        assert_eq!(joined, vec![original]);
+    }
+
+    #[test]
+    fn split_leaves_small_payload_unfragmented() {
+        let fragments = split(publish(b"abc"), FRAGMENT_BYTES);
+
+        assert_eq!(fragments, vec![publish(b"abc")]);
+    }
+
+    #[test]
+    fn out_of_order_fragment_is_rejected() {
+        let fragments = split(publish(b"0123456789"), FRAGMENT_BYTES);
+        let mut reassembler = Reassembler::new(MAX_PAYLOAD);
+
+        let result = reassembler.push(fragments[1].clone());
+
+        assert!(matches!(result, Err(FragmentError::OutOfOrder { expected: 0, received: 1 })));
+    }
+
+    #[test]
+    fn fragment_with_different_topic_is_rejected() {
+        let fragments = split(publish(b"0123456789"), FRAGMENT_BYTES);
+        let mut reassembler = Reassembler::new(MAX_PAYLOAD);
+        reassembler.push(fragments[0].clone()).unwrap();
+        let moved = pb::Publish { topic: Bytes::from_static(b"a/c"), ..fragments[1].clone() };
+
+        let result = reassembler.push(moved);
+
+        assert!(matches!(result, Err(FragmentError::Mismatched { index: 1 })));
+    }
+
+    #[test]
+    fn fragment_with_different_header_is_rejected() {
+        let fragments = split(publish(b"0123456789"), FRAGMENT_BYTES);
+        let mut reassembler = Reassembler::new(MAX_PAYLOAD);
+        reassembler.push(fragments[0].clone()).unwrap();
+        let changed = pb::Publish { header: Bytes::from_static(b"other"), ..fragments[1].clone() };
+
+        let result = reassembler.push(changed);
+
+        assert!(matches!(result, Err(FragmentError::Mismatched { index: 1 })));
+    }
+
+    #[test]
+    fn reassembled_payload_over_limit_is_rejected() {
+        let fragments = split(publish(b"0123456789"), FRAGMENT_BYTES);
+        let mut reassembler = Reassembler::new(FRAGMENT_BYTES);
+        reassembler.push(fragments[0].clone()).unwrap();
+
+        let result = reassembler.push(fragments[1].clone());
+
+        assert!(matches!(result, Err(FragmentError::TooLarge { .. })));
+    }
+}
@@ -7,12 +7,15 @@ use thiserror::Error;
 use crate::{
     auth::{AuthOutcome, Authenticator},
     client::ClientId,
+    error::ErrorCode,
     parser::pb,
 };
 
 /// Initial state: INFO has been sent to the client, CONNECT has not yet arrived.
 pub struct PendingHandshake {
     pub client_id: ClientId,
+    /// Nonce advertised in INFO; challenge credentials must sign it.
+    pub nonce: Vec<u8>,
 }
 
 /// Terminal state: CONNECT received and authentication succeeded.
@@ -37,9 +40,22 @@ pub enum HandshakeError {
     AuthenticationFailed { reason: String },
 }
 
+impl HandshakeError {
+    /// Maps the failure to the application error code reported to the client on close.
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            HandshakeError::ConnectTimeout => ErrorCode::ConnectTimeout,
+            HandshakeError::ConnectionClosed | HandshakeError::UnexpectedFrame => {
+                ErrorCode::ProtocolViolation
+            }
+            HandshakeError::AuthenticationFailed { .. } => ErrorCode::AuthFailed,
+        }
+    }
+}
+
 impl PendingHandshake {
-    pub fn new(client_id: ClientId) -> Self {
-        Self { client_id }
+    pub fn new(client_id: ClientId, nonce: Vec<u8>) -> Self {
+        Self { client_id, nonce }
     }
 
     /// Validates the CONNECT message and transitions to the completed state.
@@ -48,7 +64,7 @@ impl PendingHandshake {
         connect: pb::Connect,
         authenticator: &dyn Authenticator,
     ) -> Result<CompletedHandshake, HandshakeError> {
-        match authenticator.authenticate(&connect) {
+        match authenticator.authenticate(&connect, &self.nonce) {
             AuthOutcome::Accepted => {
                 Ok(CompletedHandshake { client_id: self.client_id, connect_info: connect })
             }
@@ -67,12 +83,18 @@ mod tests {
     #[test]
     fn on_connect_transitions_to_completed_with_no_auth() {
         let client_id = ClientId::new();
-        let pending = PendingHandshake::new(client_id);
+        let pending = PendingHandshake::new(client_id, Vec::new());
         let connect = pb::Connect {
             version: 1,
             verbose: false,
             auth_method: pb::AuthMethod::NoAuth as i32,
             credentials: None,
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
         };
         let completed = pending.on_connect(connect, &NoAuthAuthenticator).unwrap();
         assert_eq!(completed.client_id, client_id);
@@ -0,0 +1,456 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use prost::encoding::{decode_varint, encode_varint, encoded_len_varint};
+
+pub use crate::error::HeadersError;
+
+const LENGTH_PREFIX_BYTES: usize = 2;
+/// Most entries a decoded header block may hold.
+pub const MAX_HEADER_ENTRIES: usize = 64;
+/// Default for the most bytes an encoded header block may take up, entry count included.
+/// `encode_to_with` / `decode_from_with` take a different limit.
+pub const MAX_HEADER_BLOCK_BYTES: usize = 16 * 1024;
+/// Longest key accepted on insert or decode.
+pub const MAX_HEADER_KEY_BYTES: usize = 256;
+/// Longest value accepted on insert or decode.
+pub const MAX_HEADER_VALUE_BYTES: usize = 8 * 1024;
+/// Keys starting with this prefix, in any case, are reserved for headers set by Ocypode itself.
+pub const RESERVED_PREFIX: &str = "Ocypode-";
+/// Name, under `RESERVED_PREFIX`, of the producer-assigned id the server deduplicates on.
+/// A producer retrying a Publish sends the same id so the broker can drop the repeat.
+pub const MESSAGE_ID_HEADER: &str = "Msg-Id";
+/// The one control character allowed in values, as in HTTP field values.
+const HORIZONTAL_TAB: u8 = b'\t';
+
+/// Ordered header map carried in the opaque `header` field of Publish and Message.
+/// Insertion order is preserved and duplicate keys are kept, so multi-valued headers survive
+/// a roundtrip. The broker never parses headers; this type is for the applications on each end.
+///
+/// Keys compare case-insensitively (ASCII) on lookup but keep their original case on the wire.
+/// Every entry is validated on insert and on decode, so an existing `Headers` always encodes.
+///
+/// Wire format: varint entry count, then per entry a u16-length-prefixed key followed by a
+/// u16-length-prefixed value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers {
+    entries: Vec<(Bytes, Bytes)>,
+}
+
+impl Headers {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry, keeping any earlier values for the same key.
+    /// Keys in the reserved `Ocypode-` namespace are refused.
+    #[allow(dead_code)]
+    pub fn insert(
+        &mut self,
+        key: impl Into<Bytes>,
+        value: impl Into<Bytes>,
+    ) -> Result<(), HeadersError> {
+        let key = key.into();
+        if is_reserved(&key) {
+            return Err(HeadersError::ReservedKey);
+        }
+        self.push_validated(key, value.into())
+    }
+
+    /// Appends an entry under `RESERVED_PREFIX` + `name`, for headers Ocypode sets itself.
+    pub(crate) fn insert_reserved(
+        &mut self,
+        name: &str,
+        value: impl Into<Bytes>,
+    ) -> Result<(), HeadersError> {
+        self.push_validated(Bytes::from(format!("{RESERVED_PREFIX}{name}")), value.into())
+    }
+
+    /// Sets the `Ocypode-Msg-Id` entry; see `MESSAGE_ID_HEADER`.
+    #[allow(dead_code)]
+    pub fn insert_message_id(&mut self, message_id: impl Into<Bytes>) -> Result<(), HeadersError> {
+        self.insert_reserved(MESSAGE_ID_HEADER, message_id)
+    }
+
+    /// The `Ocypode-Msg-Id` value, if the producer set one.
+    pub fn message_id(&self) -> Option<&Bytes> {
+        self.get(&format!("{RESERVED_PREFIX}{MESSAGE_ID_HEADER}"))
+    }
+
+    /// First value under `key`, compared case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&Bytes> {
+        self.entries
+            .iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(key.as_bytes()))
+            .map(|(_, value)| value)
+    }
+
+    /// Every value under `key` in insertion order, compared case-insensitively.
+    #[allow(dead_code)]
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a Bytes> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(candidate, _)| candidate.eq_ignore_ascii_case(key.as_bytes()))
+            .map(|(_, value)| value)
+    }
+
+    /// Entries in insertion order, keys in their original case.
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, &Bytes)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn entries(&self) -> &[(Bytes, Bytes)] {
+        &self.entries
+    }
+
+    /// Bytes `encode_to` would write.
+    pub fn encoded_len(&self) -> usize {
+        let entry_bytes: usize = self
+            .entries
+            .iter()
+            .map(|(key, value)| LENGTH_PREFIX_BYTES + key.len() + LENGTH_PREFIX_BYTES + value.len())
+            .sum();
+        encoded_len_varint(self.entries.len() as u64) + entry_bytes
+    }
+
+    pub fn encode_to(&self, output_buffer: &mut BytesMut) -> Result<(), HeadersError> {
+        self.encode_to_with(output_buffer, MAX_HEADER_BLOCK_BYTES)
+    }
+
+    /// Like `encode_to`, refusing to write a block larger than `max_block_bytes`.
+    pub fn encode_to_with(
+        &self,
+        output_buffer: &mut BytesMut,
+        max_block_bytes: usize,
+    ) -> Result<(), HeadersError> {
+        let size = self.encoded_len();
+        if size > max_block_bytes {
+            return Err(HeadersError::HeaderBlockTooLarge { size });
+        }
+        encode_varint(self.entries.len() as u64, output_buffer);
+        for (key, value) in &self.entries {
+            put_length_prefixed(key, output_buffer);
+            put_length_prefixed(value, output_buffer);
+        }
+        Ok(())
+    }
+
+    pub fn decode_from(input_buffer: &mut impl Buf) -> Result<Self, HeadersError> {
+        Self::decode_from_with(input_buffer, MAX_HEADER_BLOCK_BYTES)
+    }
+
+    /// Like `decode_from`, rejecting a block larger than `max_block_bytes` as soon as the
+    /// running total passes it.
+    pub fn decode_from_with(
+        input_buffer: &mut impl Buf,
+        max_block_bytes: usize,
+    ) -> Result<Self, HeadersError> {
+        let remaining_before_count = input_buffer.remaining();
+        let count = decode_varint(input_buffer).map_err(|_| HeadersError::BufferTooShort)?;
+        // An overlong count such as `0x80 0x00` would give one value two encodings.
+        if remaining_before_count - input_buffer.remaining() != encoded_len_varint(count) {
+            return Err(HeadersError::NonCanonicalVarint);
+        }
+        // Checked before allocating so a forged count cannot reserve a huge Vec.
+        let count = usize::try_from(count)
+            .ok()
+            .filter(|&count| count <= MAX_HEADER_ENTRIES)
+            .ok_or(HeadersError::TooManyHeaders { count })?;
+        let mut entries = Vec::with_capacity(count);
+        let mut block_bytes = encoded_len_varint(count as u64);
+        for _ in 0..count {
+            let key = take_length_prefixed(input_buffer, &mut block_bytes, max_block_bytes)?;
+            let value = take_length_prefixed(input_buffer, &mut block_bytes, max_block_bytes)?;
+            // Reserved keys are accepted here: they are how Ocypode's own headers arrive.
+            validate_entry(&key, &value)?;
+            entries.push((key, value));
+        }
+        Ok(Self { entries })
+    }
+
+    fn push_validated(&mut self, key: Bytes, value: Bytes) -> Result<(), HeadersError> {
+        validate_entry(&key, &value)?;
+        self.entries.push((key, value));
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+fn is_reserved(key: &[u8]) -> bool {
+    key.get(..RESERVED_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(RESERVED_PREFIX.as_bytes()))
+}
+
+fn validate_entry(key: &[u8], value: &[u8]) -> Result<(), HeadersError> {
+    if key.is_empty() {
+        return Err(HeadersError::EmptyKey);
+    }
+    if key.len() > MAX_HEADER_KEY_BYTES {
+        return Err(HeadersError::KeyTooLong { length: key.len() });
+    }
+    if value.len() > MAX_HEADER_VALUE_BYTES {
+        return Err(HeadersError::ValueTooLong { length: value.len() });
+    }
+    let key_has_control = key.iter().any(u8::is_ascii_control);
+    let value_has_control =
+        value.iter().any(|&byte| byte.is_ascii_control() && byte != HORIZONTAL_TAB);
+    if key_has_control || value_has_control {
+        return Err(HeadersError::ControlCharacter);
+    }
+    Ok(())
+}
+
+// Every stored key and value passed `validate_entry`, so its length fits the u16 prefix.
+const _: () = assert!(MAX_HEADER_KEY_BYTES <= u16::MAX as usize);
+const _: () = assert!(MAX_HEADER_VALUE_BYTES <= u16::MAX as usize);
+
+fn put_length_prefixed(bytes: &Bytes, output_buffer: &mut BytesMut) {
+    output_buffer.put_u16(bytes.len() as u16);
+    output_buffer.extend_from_slice(bytes);
+}
+
+fn take_length_prefixed(
+    input_buffer: &mut impl Buf,
+    block_bytes: &mut usize,
+    max_block_bytes: usize,
+) -> Result<Bytes, HeadersError> {
+    if input_buffer.remaining() < LENGTH_PREFIX_BYTES {
+        return Err(HeadersError::BufferTooShort);
+    }
+    let length = input_buffer.get_u16() as usize;
+    *block_bytes += LENGTH_PREFIX_BYTES + length;
+    if *block_bytes > max_block_bytes {
+        return Err(HeadersError::HeaderBlockTooLarge { size: *block_bytes });
+    }
+    if input_buffer.remaining() < length {
+        return Err(HeadersError::BufferTooShort);
+    }
+    Ok(input_buffer.copy_to_bytes(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_roundtrip_preserves_duplicate_keys_in_order() {
+        let mut headers = Headers::new();
+        headers.insert("accept", "text/plain").unwrap();
+        headers.insert("trace-id", "abc").unwrap();
+        headers.insert("accept", "application/json").unwrap();
+        let mut output_buffer = BytesMut::new();
+
+        headers.encode_to(&mut output_buffer).unwrap();
+        let decoded = Headers::decode_from(&mut output_buffer.freeze()).unwrap();
+
+        assert_eq!(decoded, headers);
+    }
+
+    #[test]
+    fn get_matches_keys_case_insensitively() {
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "text/plain").unwrap();
+
+        assert_eq!(headers.get("content-type"), Some(&Bytes::from("text/plain")));
+    }
+
+    #[test]
+    fn get_all_returns_every_value_in_order() {
+        let mut headers = Headers::new();
+        headers.insert("accept", "text/plain").unwrap();
+        headers.insert("trace-id", "abc").unwrap();
+        headers.insert("Accept", "application/json").unwrap();
+
+        let values: Vec<_> = headers.get_all("ACCEPT").collect();
+
+        assert_eq!(values, [&Bytes::from("text/plain"), &Bytes::from("application/json")]);
+    }
+
+    #[test]
+    fn insert_rejects_reserved_prefix_in_any_case() {
+        let mut headers = Headers::new();
+
+        let result = headers.insert("ocypode-trace", "abc");
+
+        assert!(matches!(result, Err(HeadersError::ReservedKey)));
+    }
+
+    #[test]
+    fn insert_reserved_adds_prefixed_key() {
+        let mut headers = Headers::new();
+
+        headers.insert_reserved("Trace", "abc").unwrap();
+
+        assert_eq!(headers.get("Ocypode-Trace"), Some(&Bytes::from("abc")));
+    }
+
+    #[test]
+    fn message_id_reads_back_the_inserted_id() {
+        let mut headers = Headers::new();
+
+        headers.insert_message_id("order-17").unwrap();
+
+        assert_eq!(headers.message_id(), Some(&Bytes::from("order-17")));
+    }
+
+    #[test]
+    fn insert_rejects_control_character_in_value() {
+        let mut headers = Headers::new();
+
+        let result = headers.insert("trace-id", "abc\r\n");
+
+        assert!(matches!(result, Err(HeadersError::ControlCharacter)));
+    }
+
+    #[test]
+    fn insert_rejects_oversized_key() {
+        let mut headers = Headers::new();
+
+        let result = headers.insert("k".repeat(MAX_HEADER_KEY_BYTES + 1), "");
+
+        assert!(matches!(result, Err(HeadersError::KeyTooLong { .. })));
+    }
+
+    #[test]
+    fn decode_rejects_control_character_in_key() {
+        let mut input_buffer = BytesMut::new();
+        encode_varint(1, &mut input_buffer);
+        input_buffer.put_u16(1);
+        input_buffer.put_u8(0);
+        input_buffer.put_u16(0);
+
+        let result = Headers::decode_from(&mut input_buffer.freeze());
+
+        assert!(matches!(result, Err(HeadersError::ControlCharacter)));
+    }
+
+    #[test]
+    fn decode_truncated_headers_is_buffer_too_short() {
+        let mut headers = Headers::new();
+        headers.insert("trace-id", "abc").unwrap();
+        let mut output_buffer = BytesMut::new();
+        headers.encode_to(&mut output_buffer).unwrap();
+        output_buffer.truncate(output_buffer.len() - 1);
+
+        let result = Headers::decode_from(&mut output_buffer.freeze());
+
+        assert!(matches!(result, Err(HeadersError::BufferTooShort)));
+    }
+
+    #[test]
+    fn decode_rejects_empty_key() {
+        let mut input_buffer = BytesMut::new();
+        encode_varint(1, &mut input_buffer);
+        input_buffer.put_u16(0);
+        input_buffer.put_u16(0);
+
+        let result = Headers::decode_from(&mut input_buffer.freeze());
+
+        assert!(matches!(result, Err(HeadersError::EmptyKey)));
+    }
+
+    fn headers_with_entries(count: usize) -> Bytes {
+        let mut headers = Headers::new();
+        for i in 0..count {
+            headers.insert(format!("key-{i}"), "").unwrap();
+        }
+        let mut output_buffer = BytesMut::new();
+        headers.encode_to(&mut output_buffer).unwrap();
+        output_buffer.freeze()
+    }
+
+    #[test]
+    fn decode_accepts_exactly_max_entries() {
+        let result = Headers::decode_from(&mut headers_with_entries(MAX_HEADER_ENTRIES));
+
+        assert_eq!(result.unwrap().entries().len(), MAX_HEADER_ENTRIES);
+    }
+
+    #[test]
+    fn decode_rejects_more_than_max_entries() {
+        let result = Headers::decode_from(&mut headers_with_entries(MAX_HEADER_ENTRIES + 1));
+
+        assert!(matches!(result, Err(HeadersError::TooManyHeaders { .. })));
+    }
+
+    #[test]
+    fn decode_rejects_oversized_header_block() {
+        let mut headers = Headers::new();
+        for key in ["first", "second"] {
+            headers.insert(key, "v".repeat(MAX_HEADER_VALUE_BYTES)).unwrap();
+        }
+        let mut output_buffer = BytesMut::new();
+        headers.encode_to_with(&mut output_buffer, usize::MAX).unwrap();
+
+        let result = Headers::decode_from(&mut output_buffer.freeze());
+
+        assert!(matches!(result, Err(HeadersError::HeaderBlockTooLarge { .. })));
+    }
+
+    #[test]
+    fn encode_rejects_header_block_over_limit() {
+        let mut headers = Headers::new();
+        headers.insert("trace-id", "abc").unwrap();
+
+        let result = headers.encode_to_with(&mut BytesMut::new(), headers.encoded_len() - 1);
+
+        assert!(matches!(result, Err(HeadersError::HeaderBlockTooLarge { .. })));
+    }
+
+    #[test]
+    fn encoded_len_matches_written_bytes() {
+        let mut headers = Headers::new();
+        headers.insert("accept", "text/plain").unwrap();
+        headers.insert("trace-id", "abc").unwrap();
+        let mut output_buffer = BytesMut::new();
+
+        headers.encode_to(&mut output_buffer).unwrap();
+
+        assert_eq!(output_buffer.len(), headers.encoded_len());
+    }
+
+    #[test]
+    fn decode_key_longer_than_buffer_is_buffer_too_short() {
+        const DECLARED_KEY_LENGTH: u16 = 32;
+        let mut input_buffer = BytesMut::new();
+        encode_varint(1, &mut input_buffer);
+        input_buffer.put_u16(DECLARED_KEY_LENGTH);
+        input_buffer.extend_from_slice(b"short");
+
+        let result = Headers::decode_from(&mut input_buffer.freeze());
+
+        assert!(matches!(result, Err(HeadersError::BufferTooShort)));
+    }
+
+    #[test]
+    fn decode_rejects_overlong_count() {
+        let result = Headers::decode_from(&mut &[0x80, 0x00][..]);
+
+        assert!(matches!(result, Err(HeadersError::NonCanonicalVarint)));
+    }
+
+    #[test]
+    fn decode_accepts_canonical_zero_count() {
+        let result = Headers::decode_from(&mut &[0x00][..]);
+
+        assert_eq!(result, Ok(Headers::new()));
+    }
+
+    #[test]
+    fn canonical_two_byte_count_is_decoded_then_rejected_by_limit() {
+        const TWO_BYTE_COUNT: u64 = 16383;
+        let result = Headers::decode_from(&mut &[0xFF, 0x7F][..]);
+
+        assert!(matches!(result, Err(HeadersError::TooManyHeaders { count: TWO_BYTE_COUNT })));
+    }
+}
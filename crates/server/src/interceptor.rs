@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use crate::parser::pb;
+
+/// Decision returned by an interceptor hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Pass the (possibly modified) message on to the next interceptor.
+    Continue,
+    /// Discard the message; later interceptors are not run.
+    Drop,
+}
+
+/// Operator-supplied hook that can inspect, modify, or drop messages flowing through the server.
+/// `on_publish` runs before routing, `on_deliver` runs before a delivery is written to a subscriber.
+/// `on_extension` receives every Extension a client sends; the server itself handles none.
+pub trait Interceptor: Send + Sync + 'static {
+    fn on_publish(&self, _publish: &mut pb::Publish) -> Action {
+        Action::Continue
+    }
+
+    fn on_deliver(&self, _message: &mut pb::Message) -> Action {
+        Action::Continue
+    }
+
+    fn on_extension(&self, _extension: &pb::Extension) -> Action {
+        Action::Continue
+    }
+}
+
+/// Ordered list of interceptors, run in registration order.
+#[derive(Default)]
+pub struct InterceptorChain {
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl InterceptorChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn register(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    pub fn on_publish(&self, publish: &mut pb::Publish) -> Action {
+        for interceptor in &self.interceptors {
+            if interceptor.on_publish(publish) == Action::Drop {
+                return Action::Drop;
+            }
+        }
+        Action::Continue
+    }
+
+    pub fn on_deliver(&self, message: &mut pb::Message) -> Action {
+        for interceptor in &self.interceptors {
+            if interceptor.on_deliver(message) == Action::Drop {
+                return Action::Drop;
+            }
+        }
+        Action::Continue
+    }
+
+    /// Offers `extension` to each interceptor until one drops it, i.e. claims it as handled.
+    pub fn on_extension(&self, extension: &pb::Extension) -> Action {
+        for interceptor in &self.interceptors {
+            if interceptor.on_extension(extension) == Action::Drop {
+                return Action::Drop;
+            }
+        }
+        Action::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::headers::Headers;
+
+    const ENRICHED_KEY: &str = "x-enriched";
+    const ENRICHED_VALUE: &[u8] = b"1";
+
+    struct HeaderEnricher;
+
+    impl Interceptor for HeaderEnricher {
+        fn on_publish(&self, publish: &mut pb::Publish) -> Action {
+            let mut headers = if publish.header.is_empty() {
+                Headers::new()
+            } else {
+                Headers::decode_from(&mut &publish.header[..]).unwrap()
+            };
+            headers.insert(ENRICHED_KEY, ENRICHED_VALUE).unwrap();
+            let mut encoded = BytesMut::new();
+            headers.encode_to(&mut encoded).unwrap();
+            publish.header = encoded.freeze();
+            Action::Continue
+        }
+    }
+
+    struct DropAll;
+
+    impl Interceptor for DropAll {
+        fn on_deliver(&self, _message: &mut pb::Message) -> Action {
+            Action::Drop
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        called: AtomicBool,
+    }
+
+    impl Interceptor for Recorder {
+        fn on_deliver(&self, _message: &mut pb::Message) -> Action {
+            self.called.store(true, Ordering::Relaxed);
+            Action::Continue
+        }
+    }
+
+    struct ExtensionClaimer;
+
+    impl Interceptor for ExtensionClaimer {
+        fn on_extension(&self, _extension: &pb::Extension) -> Action {
+            Action::Drop
+        }
+    }
+
+    #[test]
+    fn unclaimed_extension_passes_through_chain() {
+        let mut chain = InterceptorChain::new();
+        chain.register(Arc::new(HeaderEnricher));
+
+        let action = chain.on_extension(&pb::Extension::default());
+
+        assert_eq!(action, Action::Continue);
+    }
+
+    #[test]
+    fn claimed_extension_is_reported_as_handled() {
+        let mut chain = InterceptorChain::new();
+        chain.register(Arc::new(ExtensionClaimer));
+
+        let action = chain.on_extension(&pb::Extension::default());
+
+        assert_eq!(action, Action::Drop);
+    }
+
+    #[test]
+    fn header_enricher_modifies_publish_header() {
+        let mut chain = InterceptorChain::new();
+        chain.register(Arc::new(HeaderEnricher));
+        let mut publish = pb::Publish::default();
+
+        chain.on_publish(&mut publish);
+
+        let headers = Headers::decode_from(&mut &publish.header[..]).unwrap();
+        assert_eq!(headers.get(ENRICHED_KEY).map(|value| &value[..]), Some(ENRICHED_VALUE));
+    }
+
+    #[test]
+    fn drop_interceptor_drops_the_message() {
+        let mut chain = InterceptorChain::new();
+        chain.register(Arc::new(DropAll));
+        chain.register(Arc::new(Recorder::default()));
+
+        let action = chain.on_deliver(&mut pb::Message::default());
+
+        assert_eq!(action, Action::Drop);
+    }
+
+    #[test]
+    fn drop_interceptor_stops_chain() {
+        let recorder = Arc::new(Recorder::default());
+        let mut chain = InterceptorChain::new();
+        chain.register(Arc::new(DropAll));
+        chain.register(Arc::clone(&recorder) as Arc<dyn Interceptor>);
+
+        chain.on_deliver(&mut pb::Message::default());
+
+        assert!(!recorder.called.load(Ordering::Relaxed));
+    }
+}
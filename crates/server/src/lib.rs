@@ -1,12 +1,27 @@
 pub mod auth;
 pub mod client;
+pub mod clock;
 pub mod config;
+pub mod dedup;
+pub mod delivery;
+pub mod dump;
 pub mod error;
+pub mod fragment;
 pub mod grpc;
 pub mod handshake;
+pub mod headers;
+pub mod interceptor;
 pub mod parser;
 pub mod permission;
+pub mod publisher;
+pub mod queue_group;
 pub mod quic;
+pub mod redact;
+pub mod request;
 pub mod router;
+pub mod subscription_index;
 pub mod topic;
+pub mod topic_alias;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub mod transport;
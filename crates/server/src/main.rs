@@ -12,17 +12,31 @@ use crate::{
 
 mod auth;
 mod client;
+mod clock;
 mod config;
+mod dedup;
+mod delivery;
 mod error;
+mod fragment;
 mod grpc;
 mod handshake;
+mod headers;
+mod interceptor;
 mod logger;
 mod metrics;
 mod parser;
 mod permission;
+mod publisher;
+mod queue_group;
 mod quic;
+mod redact;
+mod request;
 mod router;
+mod subscription_index;
 mod topic;
+mod topic_alias;
+#[cfg(feature = "trace")]
+mod trace;
 mod transport;
 
 #[tokio::main]
@@ -1,25 +1,49 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use prost::Message;
 use tokio_util::codec::{Decoder, Encoder};
 
 use crate::{
     client::ClientId,
-    error::{ClientCodecError, CodecError, ServerCodecError},
+    error::{ClientCodecError, CodecError, ErrorCode, ServerCodecError},
+    headers::MAX_HEADER_BLOCK_BYTES,
+    topic::{MAX_LAYERS, MAX_TOPIC_LENGTH},
 };
+// Variant names follow the proto, e.g. QosLevel's AT_MOST_ONCE / AT_LEAST_ONCE / EXACTLY_ONCE.
+#[allow(clippy::enum_variant_names)]
 pub mod pb {
     include!(concat!(env!("OUT_DIR"), "/ocypode.pubsub.v1.rs"));
 }
 
 const COMMAND_BYTE_LEN: usize = 1;
 const PAYLOAD_LENGTH_BYTES: usize = 4;
-const HEADER_LENGTH: usize = COMMAND_BYTE_LEN + PAYLOAD_LENGTH_BYTES;
+pub(crate) const HEADER_LENGTH: usize = COMMAND_BYTE_LEN + PAYLOAD_LENGTH_BYTES;
 // Maximum payload is 1MiB.
 pub const MAXIMUM_PAYLOAD_BYTES: usize = 1024 * 1024;
+/// Room for the topic, ids, and protobuf tags around a maximum-size payload and header.
+const FRAME_ENVELOPE_BYTES: usize = 1024;
+/// Largest frame payload either codec accepts by default.
+pub const MAXIMUM_FRAME_PAYLOAD_BYTES: usize = MAXIMUM_PAYLOAD_BYTES + FRAME_ENVELOPE_BYTES;
 /// Current Ocypode protocol version.
 pub const PROTOCOL_VERSION: u32 = 1;
+/// Longest queue group name accepted in SUBSCRIBE, in bytes.
+pub const MAX_QUEUE_GROUP_LENGTH: usize = 128;
+/// Highest priority a Publish or Message may carry; 0 is the lowest.
+pub const MAX_PRIORITY: u32 = 7;
+/// Most entries a PublishBatch may carry; each becomes its own frame for the reader.
+pub const MAX_BATCH_ENTRIES: usize = 1024;
 
 /// Command classify Ocypode protocol.
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Info = 0x00,
     Connect = 0x01,
@@ -27,46 +51,192 @@ pub enum Command {
     Subscribe = 0x03,
     UnSubscribe = 0x04,
     Message = 0x05,
-    // TODO: add Err command.
+    Ack = 0x06,
+    Error = 0x07,
+    Ping = 0x08,
+    Pong = 0x09,
+    Disconnect = 0x0A,
+    SubAck = 0x0B,
+    PublishBatch = 0x0C,
+    Flow = 0x0D,
+    /// Carries a sub-command outside the core set; see `pb::Extension`.
+    Extension = 0x0E,
+    MessageAck = 0x0F,
+}
+
+#[allow(dead_code)]
+impl Command {
+    /// True for commands a client sends to the server.
+    pub fn is_client_to_server(self) -> bool {
+        match self {
+            Command::Connect
+            | Command::Publish
+            | Command::Subscribe
+            | Command::UnSubscribe
+            | Command::Ping
+            | Command::Disconnect
+            | Command::PublishBatch
+            | Command::Flow
+            | Command::MessageAck
+            | Command::Extension => true,
+            Command::Info
+            | Command::Message
+            | Command::Ack
+            | Command::Error
+            | Command::Pong
+            | Command::SubAck => false,
+        }
+    }
+
+    /// True for commands the server sends to a client.
+    pub fn is_server_to_client(self) -> bool {
+        match self {
+            Command::Info
+            | Command::Message
+            | Command::Ack
+            | Command::Error
+            | Command::Pong
+            | Command::SubAck
+            | Command::Disconnect
+            | Command::Extension => true,
+            Command::Connect
+            | Command::Publish
+            | Command::Subscribe
+            | Command::UnSubscribe
+            | Command::Ping
+            | Command::PublishBatch
+            | Command::Flow
+            | Command::MessageAck => false,
+        }
+    }
 }
 
 /// Command trait for payload encode/decode.
 pub trait CommandCodec: Message + Default + Sized {
-    const COMMAND: u8;
+    const COMMAND: Command;
+
+    /// Exact number of bytes `encode_frame` writes for this item, header included.
+    /// The payload part is prost's `encoded_len`, so no per-command implementation is needed.
+    fn frame_len(&self) -> usize {
+        HEADER_LENGTH + self.encoded_len()
+    }
+
+    /// Application bytes (payload plus header) counted against `max_payload`, or `None` for
+    /// frames that carry no application data.
+    fn application_len(&self) -> Option<usize> {
+        None
+    }
 
+    #[allow(dead_code)]
     fn encode_payload(&self) -> Result<Bytes, CodecError> {
         let mut payload_buffer = Vec::with_capacity(self.encoded_len());
         self.encode(&mut payload_buffer)?;
         Ok(Bytes::from(payload_buffer))
     }
 
-    fn decode_payload(payload: &[u8]) -> Result<Self, CodecError> {
-        Ok(Self::decode(payload)?)
+    /// Decodes a frame payload; failures name the command alongside prost's field path.
+    /// Taking `Bytes` lets `bytes` fields (see build.rs) slice the frame instead of copying.
+    fn decode_payload(payload: Bytes) -> Result<Self, CodecError> {
+        Self::decode(payload)
+            .map_err(|source| CodecError::DecodePayload { command: Self::COMMAND, source })
     }
 }
 
 impl CommandCodec for pb::Info {
-    const COMMAND: u8 = Command::Info as u8;
+    const COMMAND: Command = Command::Info;
 }
 
 impl CommandCodec for pb::Connect {
-    const COMMAND: u8 = Command::Connect as u8;
+    const COMMAND: Command = Command::Connect;
 }
 
 impl CommandCodec for pb::Publish {
-    const COMMAND: u8 = Command::Publish as u8;
+    const COMMAND: Command = Command::Publish;
+
+    fn application_len(&self) -> Option<usize> {
+        Some(self.payload.len() + self.header.len())
+    }
+}
+
+impl CommandCodec for pb::PublishBatch {
+    const COMMAND: Command = Command::PublishBatch;
+
+    /// The limit applies to each entry, so a batch reports its largest one.
+    fn application_len(&self) -> Option<usize> {
+        self.publishes.iter().filter_map(CommandCodec::application_len).max()
+    }
+}
+
+impl CommandCodec for pb::Flow {
+    const COMMAND: Command = Command::Flow;
+}
+
+impl CommandCodec for pb::MessageAck {
+    const COMMAND: Command = Command::MessageAck;
+}
+
+impl CommandCodec for pb::Extension {
+    const COMMAND: Command = Command::Extension;
+
+    fn application_len(&self) -> Option<usize> {
+        Some(self.payload.len())
+    }
 }
 
 impl CommandCodec for pb::Subscribe {
-    const COMMAND: u8 = Command::Subscribe as u8;
+    const COMMAND: Command = Command::Subscribe;
+}
+
+impl pb::Subscribe {
+    /// The queue group name, or `None` when the subscription is not part of a group.
+    #[allow(dead_code)]
+    pub fn queue_group_str(&self) -> Option<&str> {
+        self.queue_group.as_deref()
+    }
 }
 
 impl CommandCodec for pb::UnSubscribe {
-    const COMMAND: u8 = Command::UnSubscribe as u8;
+    const COMMAND: Command = Command::UnSubscribe;
 }
 
 impl CommandCodec for pb::Message {
-    const COMMAND: u8 = Command::Message as u8;
+    const COMMAND: Command = Command::Message;
+
+    fn application_len(&self) -> Option<usize> {
+        Some(self.payload.len() + self.header.len())
+    }
+}
+
+impl CommandCodec for pb::Ack {
+    const COMMAND: Command = Command::Ack;
+}
+
+impl CommandCodec for pb::Error {
+    const COMMAND: Command = Command::Error;
+}
+
+impl pb::Error {
+    /// The typed error code. Unrecognised codes are reported as `CodecError::UnknownErrorCode`.
+    #[allow(dead_code)]
+    pub fn error_code(&self) -> Result<ErrorCode, CodecError> {
+        ErrorCode::try_from(self.code)
+    }
+}
+
+impl CommandCodec for pb::Ping {
+    const COMMAND: Command = Command::Ping;
+}
+
+impl CommandCodec for pb::Pong {
+    const COMMAND: Command = Command::Pong;
+}
+
+impl CommandCodec for pb::Disconnect {
+    const COMMAND: Command = Command::Disconnect;
+}
+
+impl CommandCodec for pb::SubAck {
+    const COMMAND: Command = Command::SubAck;
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -75,73 +245,96 @@ pub enum Frame {
     Publish(pb::Publish),
     Subscribe(pb::Subscribe),
     UnSubscribe(pb::UnSubscribe),
+    Flow(pb::Flow),
+    MessageAck(pb::MessageAck),
+    Ping(pb::Ping),
+    Disconnect(pb::Disconnect),
+    Extension(pb::Extension),
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq)]
-pub enum ClientFrame {
-    Info(pb::Info),
-    Message(pb::Message),
+impl Frame {
+    pub fn command(&self) -> Command {
+        match self {
+            Frame::Connect(_) => Command::Connect,
+            Frame::Publish(_) => Command::Publish,
+            Frame::Subscribe(_) => Command::Subscribe,
+            Frame::UnSubscribe(_) => Command::UnSubscribe,
+            Frame::Flow(_) => Command::Flow,
+            Frame::MessageAck(_) => Command::MessageAck,
+            Frame::Ping(_) => Command::Ping,
+            Frame::Disconnect(_) => Command::Disconnect,
+            Frame::Extension(_) => Command::Extension,
+        }
+    }
 }
 
-/// Messages the server sends to a connected client.
-/// Used as the element type for the outbound write-buffer channel.
 #[allow(dead_code)]
-pub enum OutboundMessage {
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientFrame {
     Info(pb::Info),
     Message(pb::Message),
-    // TODO: Pong, Error(pb::Error), etc.
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ServerInboundCommand {
-    Connect,
-    Publish,
-    Subscribe,
-    UnSubscribe,
+    Ack(pb::Ack),
+    Error(pb::Error),
+    Pong(pb::Pong),
+    SubAck(pb::SubAck),
+    Disconnect(pb::Disconnect),
+    Extension(pb::Extension),
 }
 
-impl TryFrom<u8> for ServerInboundCommand {
-    type Error = ();
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            _ if value == <pb::Connect as CommandCodec>::COMMAND => {
-                Ok(ServerInboundCommand::Connect)
-            }
-            _ if value == <pb::Publish as CommandCodec>::COMMAND => {
-                Ok(ServerInboundCommand::Publish)
-            }
-            _ if value == <pb::Subscribe as CommandCodec>::COMMAND => {
-                Ok(ServerInboundCommand::Subscribe)
-            }
-            _ if value == <pb::UnSubscribe as CommandCodec>::COMMAND => {
-                Ok(ServerInboundCommand::UnSubscribe)
-            }
-            _ => Err(()),
+#[allow(dead_code)]
+impl ClientFrame {
+    pub fn command(&self) -> Command {
+        match self {
+            ClientFrame::Info(_) => Command::Info,
+            ClientFrame::Message(_) => Command::Message,
+            ClientFrame::Ack(_) => Command::Ack,
+            ClientFrame::Error(_) => Command::Error,
+            ClientFrame::Pong(_) => Command::Pong,
+            ClientFrame::SubAck(_) => Command::SubAck,
+            ClientFrame::Disconnect(_) => Command::Disconnect,
+            ClientFrame::Extension(_) => Command::Extension,
         }
     }
 }
 
+/// Messages the server sends to a connected client.
+/// Used as the element type for the outbound write-buffer channel.
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ClientInboundCommand {
-    Info,
-    Message,
-}
-
-impl TryFrom<u8> for ClientInboundCommand {
-    type Error = ();
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            _ if value == <pb::Info as CommandCodec>::COMMAND => Ok(ClientInboundCommand::Info),
-            _ if value == <pb::Message as CommandCodec>::COMMAND => {
-                Ok(ClientInboundCommand::Message)
-            }
-            _ => Err(()),
-        }
-    }
+pub enum OutboundMessage {
+    Info(pb::Info),
+    /// A delivery to a subscriber, stamped with the session clock when it was queued.
+    /// `publish_qos` is what the publisher asked for; the writer lowers it to what the
+    /// subscription accepts.
+    Message {
+        message: pb::Message,
+        enqueued_at_ms: u64,
+        publish_qos: pb::QosLevel,
+    },
+    /// Confirms a Publish on a verbose connection.
+    Ack(pb::Ack),
+    Error(pb::Error),
+    Pong(pb::Pong),
+    /// Answers a Subscribe on a verbose connection.
+    SubAck(pb::SubAck),
+    Disconnect(pb::Disconnect),
+    #[allow(dead_code)]
+    Extension(pb::Extension),
+    /// Wakes the writer after a Flow grant so it can release deliveries paused for lack of
+    /// credit. Nothing is written to the wire.
+    CreditsGranted {
+        subscription_id: u32,
+    },
+    /// Tells the writer an UnSubscribe ended the subscription, so it discards deliveries still
+    /// held for it or awaiting their ack. Nothing is written to the wire.
+    SubscriptionClosed {
+        subscription_id: u32,
+    },
+    /// Hands a MessageAck to the writer, which tracks unacknowledged deliveries. Nothing is
+    /// written to the wire.
+    DeliveryAcknowledged {
+        delivery_id: u64,
+    },
 }
 
 /// Server outbound message builder
@@ -165,9 +358,76 @@ impl ServerOutbound {
             client_id: client_id.0,
             requires_auth,
             tls_verify,
+            max_keepalive_secs: 0,
+            nonce: Vec::new(),
+            topic_alias_maximum: 0,
+            max_reassembled_payload: 0,
+            connect_urls: Vec::new(),
+            max_topic_length: Some(MAX_TOPIC_LENGTH as u32),
+            max_topic_layers: Some(MAX_LAYERS as u32),
+            max_header_bytes: Some(MAX_HEADER_BLOCK_BYTES as u32),
+            // No per-connection subscription limit is enforced yet.
+            max_subscriptions: None,
+        }
+    }
+
+    /// Creates an ACK confirming the Publish identified by `publish_id`
+    pub fn ack(publish_id: u64) -> pb::Ack {
+        pb::Ack { publish_id }
+    }
+
+    /// Creates an ERROR for the Publish identified by `publish_id` (0 when not tied to one).
+    /// It is marked fatal when `code` always ends the connection; see `ErrorCode::is_fatal`.
+    pub fn error(publish_id: u64, code: ErrorCode, reason: Option<String>) -> pb::Error {
+        pb::Error { publish_id, code: code as u32, reason, fatal: code.is_fatal() }
+    }
+
+    /// Creates an ERROR sent just before the server closes the connection
+    #[allow(dead_code)]
+    pub fn fatal_error(code: ErrorCode, reason: Option<String>) -> pb::Error {
+        pb::Error { fatal: true, ..Self::error(0, code, reason) }
+    }
+
+    /// Creates the MESSAGE delivering `publish` to `subscription_id`, stamped with the time
+    /// the broker received it. The writer strips the stamp for connections that did not set
+    /// `Connect.receive_timestamps`.
+    #[allow(dead_code)]
+    pub fn message(
+        publish: &pb::Publish,
+        subscription_id: u32,
+        received_at_ms: u64,
+    ) -> pb::Message {
+        pb::Message {
+            topic: publish.topic.clone(),
+            subscription_id,
+            payload: publish.payload.clone(),
+            header: publish.header.clone(),
+            reply_to: publish.reply_to.clone(),
+            sequence: None,
+            retained: false,
+            fragment: None,
+            priority: publish.priority,
+            received_at_ms: Some(received_at_ms),
+            delivery_id: None,
         }
     }
 
+    /// Creates a SUBACK granting the Subscribe identified by `subscription_id`
+    pub fn sub_ack(subscription_id: u32) -> pb::SubAck {
+        pb::SubAck { subscription_id, granted: true, code: None }
+    }
+
+    /// Creates a SUBACK denying the Subscribe identified by `subscription_id`
+    pub fn sub_denied(subscription_id: u32, code: ErrorCode) -> pb::SubAck {
+        pb::SubAck { subscription_id, granted: false, code: Some(code as u32) }
+    }
+
+    /// Creates a DISCONNECT announcing that the server is about to close the connection
+    #[allow(dead_code)]
+    pub fn disconnect(reason: pb::DisconnectReason, text: Option<String>) -> pb::Disconnect {
+        pb::Disconnect { reason: reason as i32, text }
+    }
+
     /// Creates a default INFO message
     /// TODO: Load INFO message from configuration instead of using dummy values
     #[allow(dead_code)]
@@ -196,6 +456,12 @@ impl ClientOutbound {
             verbose,
             auth_method: pb::AuthMethod::NoAuth as i32,
             credentials: None,
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
         }
     }
 
@@ -215,8 +481,132 @@ impl ClientOutbound {
                 username,
                 password,
             })),
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
+        }
+    }
+
+    /// Creates a CONNECT message with a bearer token
+    #[allow(dead_code)]
+    pub fn connect_with_token(version: u32, verbose: bool, token: Vec<u8>) -> pb::Connect {
+        pb::Connect {
+            auth_method: pb::AuthMethod::Token as i32,
+            credentials: Some(pb::connect::Credentials::BearerToken(pb::BearerToken { token })),
+            ..Self::connect(version, verbose)
+        }
+    }
+
+    /// Creates a CONNECT message with challenge credentials
+    #[allow(dead_code)]
+    pub fn connect_with_challenge(
+        version: u32,
+        verbose: bool,
+        public_key: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> pb::Connect {
+        pb::Connect {
+            version,
+            verbose,
+            auth_method: pb::AuthMethod::Challenge as i32,
+            credentials: Some(pb::connect::Credentials::ChallengeAuth(pb::ChallengeAuth {
+                public_key,
+                signature,
+            })),
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
+        }
+    }
+}
+
+/// Auth methods added after this server was built decode as plain integers; they are
+/// rejected here rather than falling through to the authenticator.
+fn validate_auth_method(connect: &pb::Connect) -> Result<(), CodecError> {
+    pb::AuthMethod::try_from(connect.auth_method)
+        .map(|_| ())
+        .map_err(|_| CodecError::UnknownAuthMethod(connect.auth_method))
+}
+
+/// Like auth methods, QoS levels decode as plain integers; unknown ones are rejected here
+/// so the delivery path only ever sees a `pb::QosLevel`.
+fn validate_qos(qos: i32) -> Result<(), CodecError> {
+    pb::QosLevel::try_from(qos).map(|_| ()).map_err(|_| CodecError::UnknownQos(qos))
+}
+
+/// Priority travels as a plain uint32 on the wire, so values above `MAX_PRIORITY` are
+/// rejected here rather than silently clamped.
+fn validate_priority(priority: Option<u32>) -> Result<(), CodecError> {
+    match priority {
+        Some(priority) if priority > MAX_PRIORITY => Err(CodecError::InvalidPriority(priority)),
+        _ => Ok(()),
+    }
+}
+
+/// Empty entries cost a few bytes each on the wire, so the frame size alone would let one
+/// batch expand into hundreds of thousands of Publish frames.
+fn validate_batch_entries(batch: &pb::PublishBatch) -> Result<(), CodecError> {
+    let entries = batch.publishes.len();
+    if entries > MAX_BATCH_ENTRIES {
+        return Err(CodecError::TooManyBatchEntries { entries, max: MAX_BATCH_ENTRIES });
+    }
+    Ok(())
+}
+
+/// An empty group name would key every such subscription into one unnamed group,
+/// so a queue group, when present, must be non-empty and within `MAX_QUEUE_GROUP_LENGTH`.
+fn validate_queue_group(subscribe: &pb::Subscribe) -> Result<(), CodecError> {
+    match subscribe.queue_group_str() {
+        Some(group) if group.is_empty() || group.len() > MAX_QUEUE_GROUP_LENGTH => {
+            Err(CodecError::InvalidQueueGroup { length: group.len() })
         }
+        _ => Ok(()),
+    }
+}
+
+/// Writes `item` as a complete frame: command byte, payload length, then payload.
+pub fn encode_frame<T: CommandCodec>(
+    item: &T,
+    output_buffer: &mut BytesMut,
+) -> Result<(), CodecError> {
+    // The header has a fixed width, so the payload is encoded in place behind it
+    // instead of into a separate buffer that would then be copied.
+    let encoded_length = item.encoded_len();
+    let payload_length: u32 =
+        encoded_length.try_into().map_err(|_| CodecError::InvalidSizeBytes(encoded_length))?;
+
+    output_buffer.reserve(item.frame_len());
+    output_buffer.put_u8(T::COMMAND as u8);
+    output_buffer.put_u32(payload_length);
+    item.encode(output_buffer)?;
+    Ok(())
+}
+
+/// Payload and header together count toward the advertised `max_payload`.
+fn check_payload_size(size: usize, max_payload: usize) -> Result<(), CodecError> {
+    if size > max_payload {
+        return Err(CodecError::PayloadTooLarge { size, max: max_payload });
+    }
+    Ok(())
+}
+
+/// Like `encode_frame`, but first rejects an item whose application bytes exceed
+/// `max_payload`, so an oversized Publish or Message fails locally instead of on the peer.
+fn encode_checked_frame<T: CommandCodec>(
+    item: &T,
+    max_payload: usize,
+    output_buffer: &mut BytesMut,
+) -> Result<(), CodecError> {
+    if let Some(size) = item.application_len() {
+        check_payload_size(size, max_payload)?;
     }
+    encode_frame(item, output_buffer)
 }
 
 fn parse_header(incoming_bytes: &BytesMut) -> Option<(u8, usize)> {
@@ -230,154 +620,449 @@ fn parse_header(incoming_bytes: &BytesMut) -> Option<(u8, usize)> {
     Some((command, payload_length))
 }
 
-pub struct ServerCodec;
+/// Splits the next complete frame off `incoming_bytes`, shared by both codecs, and returns it
+/// with the decoder registered for its command. Command bytes missing from `decoders` are
+/// skipped one at a time to resync, and a declared length over `max_frame_payload` is
+/// rejected before the payload is buffered.
+fn split_frame<F>(
+    incoming_bytes: &mut BytesMut,
+    decoders: &[(Command, PayloadDecoder<F>)],
+    max_frame_payload: usize,
+) -> Result<Option<(PayloadDecoder<F>, Bytes)>, CodecError> {
+    loop {
+        let Some((command, payload_length)) = parse_header(incoming_bytes) else {
+            return Ok(None);
+        };
 
-impl Decoder for ServerCodec {
-    type Item = Frame;
-    type Error = ServerCodecError;
+        let Some(&(_, decode)) =
+            decoders.iter().find(|(registered, _)| *registered as u8 == command)
+        else {
+            // Drop one byte to resync on an unexpected frame.
+            incoming_bytes.advance(1);
+            continue;
+        };
 
-    fn decode(&mut self, incoming_bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        loop {
-            let Some((command, payload_length)) = parse_header(incoming_bytes) else {
-                return Ok(None);
-            };
+        if payload_length > max_frame_payload {
+            return Err(CodecError::FrameTooLarge {
+                payload_length,
+                max_payload: max_frame_payload,
+            });
+        }
 
-            let command = match ServerInboundCommand::try_from(command) {
-                Ok(value) => value,
-                Err(()) => {
-                    // Drop one byte to resync on an unexpected frame.
-                    incoming_bytes.advance(1);
-                    continue;
-                }
-            };
+        let frame_length = HEADER_LENGTH + payload_length;
+        if incoming_bytes.len() < frame_length {
+            return Ok(None);
+        }
 
-            if payload_length > MAXIMUM_PAYLOAD_BYTES {
-                // Invalid length; drop one byte and try to recover.
-                incoming_bytes.advance(1);
-                continue;
-            }
+        incoming_bytes.advance(HEADER_LENGTH);
+        return Ok(Some((decode, incoming_bytes.split_to(payload_length).freeze())));
+    }
+}
 
-            let frame_length = HEADER_LENGTH + payload_length;
-            if incoming_bytes.len() < frame_length {
-                return Ok(None);
-            }
+/// What a payload decoder produced: usually one frame, or every entry of a batch command.
+pub enum Decoded<F> {
+    One(F),
+    Many(Vec<F>),
+}
 
-            incoming_bytes.advance(HEADER_LENGTH);
-            let payload_bytes = incoming_bytes.split_to(payload_length);
-            let frame = match command {
-                ServerInboundCommand::Connect => {
-                    Frame::Connect(pb::Connect::decode_payload(&payload_bytes)?)
-                }
-                ServerInboundCommand::Publish => {
-                    Frame::Publish(pb::Publish::decode_payload(&payload_bytes)?)
-                }
-                ServerInboundCommand::Subscribe => {
-                    Frame::Subscribe(pb::Subscribe::decode_payload(&payload_bytes)?)
-                }
-                ServerInboundCommand::UnSubscribe => {
-                    Frame::UnSubscribe(pb::UnSubscribe::decode_payload(&payload_bytes)?)
-                }
-            };
-            return Ok(Some(frame));
-        }
-    }
+/// Decodes the payload of one command, applying the codec's limits.
+pub type PayloadDecoder<F> = fn(Bytes, &CodecLimits) -> Result<Decoded<F>, CodecError>;
+
+/// One side of a connection: the frames it receives and how each command is decoded.
+/// Commands missing from `DECODERS` are sent by this side, never received.
+pub trait Direction {
+    type Frame: 'static;
+    type Error: From<CodecError> + From<io::Error>;
+
+    const DECODERS: &'static [(Command, PayloadDecoder<Self::Frame>)];
 }
 
-impl<T> Encoder<T> for ServerCodec
-where
-    T: CommandCodec,
-{
+/// The server's side: decodes what clients send.
+pub enum ServerSide {}
+
+/// A client's side: decodes what the server sends.
+#[allow(dead_code)]
+pub enum ClientSide {}
+
+impl Direction for ServerSide {
+    type Frame = Frame;
     type Error = ServerCodecError;
 
-    fn encode(&mut self, item: T, output_buffer: &mut BytesMut) -> Result<(), Self::Error> {
-        let payload = item.encode_payload()?;
-        let payload_length: u32 =
-            payload.len().try_into().map_err(|_| CodecError::InvalidSizeBytes(payload.len()))?;
-
-        output_buffer.reserve(HEADER_LENGTH + payload.len());
-        output_buffer.put_u8(T::COMMAND);
-        output_buffer.put_u32(payload_length);
-        output_buffer.extend_from_slice(&payload);
+    const DECODERS: &'static [(Command, PayloadDecoder<Frame>)] = &[
+        (pb::Connect::COMMAND, |payload, _| {
+            let connect = pb::Connect::decode_payload(payload)?;
+            validate_auth_method(&connect)?;
+            Ok(Decoded::One(Frame::Connect(connect)))
+        }),
+        (pb::Publish::COMMAND, |payload, limits| {
+            let publish = pb::Publish::decode_payload(payload)?;
+            limits.check(&publish.payload, &publish.header)?;
+            validate_qos(publish.qos)?;
+            validate_priority(publish.priority)?;
+            Ok(Decoded::One(Frame::Publish(publish)))
+        }),
+        (pb::PublishBatch::COMMAND, |payload, limits| {
+            let batch = pb::PublishBatch::decode_payload(payload)?;
+            validate_batch_entries(&batch)?;
+            for publish in &batch.publishes {
+                limits.check(&publish.payload, &publish.header)?;
+                validate_qos(publish.qos)?;
+                validate_priority(publish.priority)?;
+            }
+            Ok(Decoded::Many(batch.publishes.into_iter().map(Frame::Publish).collect()))
+        }),
+        (pb::Subscribe::COMMAND, |payload, _| {
+            let subscribe = pb::Subscribe::decode_payload(payload)?;
+            validate_queue_group(&subscribe)?;
+            validate_qos(subscribe.qos)?;
+            Ok(Decoded::One(Frame::Subscribe(subscribe)))
+        }),
+        (pb::UnSubscribe::COMMAND, |payload, _| {
+            Ok(Decoded::One(Frame::UnSubscribe(pb::UnSubscribe::decode_payload(payload)?)))
+        }),
+        (pb::Flow::COMMAND, |payload, _| {
+            Ok(Decoded::One(Frame::Flow(pb::Flow::decode_payload(payload)?)))
+        }),
+        (pb::MessageAck::COMMAND, |payload, _| {
+            Ok(Decoded::One(Frame::MessageAck(pb::MessageAck::decode_payload(payload)?)))
+        }),
+        (pb::Extension::COMMAND, |payload, limits| {
+            let extension = pb::Extension::decode_payload(payload)?;
+            limits.check(&extension.payload, &[])?;
+            Ok(Decoded::One(Frame::Extension(extension)))
+        }),
+        (pb::Ping::COMMAND, |payload, _| {
+            Ok(Decoded::One(Frame::Ping(pb::Ping::decode_payload(payload)?)))
+        }),
+        (pb::Disconnect::COMMAND, |payload, _| {
+            Ok(Decoded::One(Frame::Disconnect(pb::Disconnect::decode_payload(payload)?)))
+        }),
+    ];
+}
+
+impl Direction for ClientSide {
+    type Frame = ClientFrame;
+    type Error = ClientCodecError;
+
+    const DECODERS: &'static [(Command, PayloadDecoder<ClientFrame>)] = &[
+        (pb::Info::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::Info(pb::Info::decode_payload(payload)?)))
+        }),
+        (pb::Message::COMMAND, |payload, limits| {
+            let message = pb::Message::decode_payload(payload)?;
+            limits.check(&message.payload, &message.header)?;
+            validate_priority(message.priority)?;
+            Ok(Decoded::One(ClientFrame::Message(message)))
+        }),
+        (pb::Ack::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::Ack(pb::Ack::decode_payload(payload)?)))
+        }),
+        (pb::Error::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::Error(pb::Error::decode_payload(payload)?)))
+        }),
+        (pb::Pong::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::Pong(pb::Pong::decode_payload(payload)?)))
+        }),
+        (pb::SubAck::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::SubAck(pb::SubAck::decode_payload(payload)?)))
+        }),
+        (pb::Disconnect::COMMAND, |payload, _| {
+            Ok(Decoded::One(ClientFrame::Disconnect(pb::Disconnect::decode_payload(payload)?)))
+        }),
+        (pb::Extension::COMMAND, |payload, limits| {
+            let extension = pb::Extension::decode_payload(payload)?;
+            limits.check(&extension.payload, &[])?;
+            Ok(Decoded::One(ClientFrame::Extension(extension)))
+        }),
+    ];
+}
+
+/// Size limits a codec enforces, the same in both directions.
+#[derive(Debug, Clone, Copy)]
+pub struct CodecLimits {
+    /// Largest payload plus header in a Publish or Message, decoded or encoded.
+    max_payload: usize,
+    /// Largest `header` field in a decoded Publish or Message.
+    max_header_bytes: usize,
+    /// Largest payload length a frame header may declare.
+    max_frame_payload: usize,
+}
+
+impl CodecLimits {
+    /// Checks the application bytes of one decoded Publish or Message.
+    fn check(&self, payload: &[u8], header: &[u8]) -> Result<(), CodecError> {
+        check_payload_size(payload.len() + header.len(), self.max_payload)?;
+        if header.len() > self.max_header_bytes {
+            return Err(CodecError::HeadersTooLarge {
+                size: header.len(),
+                max: self.max_header_bytes,
+            });
+        }
         Ok(())
     }
 }
 
+impl Default for CodecLimits {
+    fn default() -> Self {
+        Self {
+            max_payload: MAXIMUM_PAYLOAD_BYTES,
+            max_header_bytes: MAX_HEADER_BLOCK_BYTES,
+            max_frame_payload: MAXIMUM_FRAME_PAYLOAD_BYTES,
+        }
+    }
+}
+
+/// Frame codec for one side of a connection. Framing, limits and encoding are shared; only
+/// the `Direction`'s decoder table differs. Batch commands are expanded here, so callers
+/// only ever see individual frames.
+pub struct Codec<D: Direction> {
+    /// Frames of the last batch not yet handed out.
+    pending: VecDeque<D::Frame>,
+    limits: CodecLimits,
+}
+
+/// Server-side codec: decodes `Frame`s sent by clients.
+pub type ServerCodec = Codec<ServerSide>;
+
+/// Client-side codec: decodes `ClientFrame`s sent by the server.
 #[allow(dead_code)]
-pub struct ClientCodec;
+pub type ClientCodec = Codec<ClientSide>;
 
-impl Decoder for ClientCodec {
-    type Item = ClientFrame;
-    type Error = ClientCodecError;
+impl<D: Direction> Codec<D> {
+    /// Sets the largest `header` field accepted on a decoded Publish or Message.
+    pub fn with_max_header_bytes(mut self, max_header_bytes: usize) -> Self {
+        self.limits.max_header_bytes = max_header_bytes;
+        self
+    }
 
-    fn decode(&mut self, incoming_bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        loop {
-            let Some((command, payload_length)) = parse_header(incoming_bytes) else {
-                return Ok(None);
-            };
+    /// Lowers (or raises) the frame size limit. Frames declaring a larger payload are
+    /// rejected from their header alone, before any of the payload is buffered.
+    pub fn with_max_frame_payload(mut self, max_frame_payload: usize) -> Self {
+        self.limits.max_frame_payload = max_frame_payload;
+        self
+    }
 
-            let command = match ClientInboundCommand::try_from(command) {
-                Ok(value) => value,
-                Err(()) => {
-                    // Drop one byte to resync on an unexpected frame.
-                    incoming_bytes.advance(1);
-                    continue;
-                }
-            };
+    /// Sets the payload limit, applied to Publishes and Messages in both directions.
+    #[allow(dead_code)]
+    pub fn with_max_payload(mut self, max_payload: usize) -> Self {
+        self.limits.max_payload = max_payload;
+        self
+    }
 
-            if payload_length > MAXIMUM_PAYLOAD_BYTES {
-                // Invalid length; drop one byte and try to recover.
-                incoming_bytes.advance(1);
-                continue;
-            }
+    /// Adopts a new payload limit on a live codec, e.g. the one a client reads from the
+    /// server's Info.
+    #[allow(dead_code)]
+    pub fn set_max_payload(&mut self, max_payload: usize) {
+        self.limits.max_payload = max_payload;
+    }
 
-            let frame_length = HEADER_LENGTH + payload_length;
-            if incoming_bytes.len() < frame_length {
-                return Ok(None);
-            }
+    /// Appends `item` as one frame to a caller-owned buffer, so pooled buffers can be reused
+    /// and several frames written to the stream at once. Nothing is cloned or allocated
+    /// beyond growing `output_buffer`. Publishes and Messages over `max_payload` fail here
+    /// rather than on the peer.
+    pub fn encode_into<T: CommandCodec>(
+        &self,
+        item: &T,
+        output_buffer: &mut BytesMut,
+    ) -> Result<(), D::Error> {
+        Ok(encode_checked_frame(item, self.limits.max_payload, output_buffer)?)
+    }
+}
+
+impl<D: Direction> Default for Codec<D> {
+    fn default() -> Self {
+        Self { pending: VecDeque::new(), limits: CodecLimits::default() }
+    }
+}
+
+impl<D: Direction> Decoder for Codec<D> {
+    type Item = D::Frame;
+    type Error = D::Error;
 
-            incoming_bytes.advance(HEADER_LENGTH);
-            let payload_bytes = incoming_bytes.split_to(payload_length);
-            let frame = match command {
-                ClientInboundCommand::Info => {
-                    ClientFrame::Info(pb::Info::decode_payload(&payload_bytes)?)
-                }
-                ClientInboundCommand::Message => {
-                    ClientFrame::Message(pb::Message::decode_payload(&payload_bytes)?)
-                }
+    fn decode(&mut self, incoming_bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Ok(Some(frame));
+            }
+            let Some((decode, payload_bytes)) =
+                split_frame(incoming_bytes, D::DECODERS, self.limits.max_frame_payload)?
+            else {
+                return Ok(None);
             };
-            return Ok(Some(frame));
+            match decode(payload_bytes, &self.limits)? {
+                Decoded::One(frame) => return Ok(Some(frame)),
+                // An empty batch yields nothing; the loop looks for the next frame instead.
+                Decoded::Many(frames) => self.pending.extend(frames),
+            }
         }
     }
 }
 
-impl<T> Encoder<T> for ClientCodec
+impl<D, T> Encoder<T> for Codec<D>
 where
+    D: Direction,
     T: CommandCodec,
 {
-    type Error = ClientCodecError;
+    type Error = D::Error;
 
     fn encode(&mut self, item: T, output_buffer: &mut BytesMut) -> Result<(), Self::Error> {
-        let payload = item.encode_payload()?;
-        let payload_length: u32 =
-            payload.len().try_into().map_err(|_| CodecError::InvalidSizeBytes(payload.len()))?;
-
-        output_buffer.reserve(HEADER_LENGTH + payload.len());
-        output_buffer.put_u8(T::COMMAND);
-        output_buffer.put_u32(payload_length);
-        output_buffer.extend_from_slice(&payload);
+        self.encode_into(&item, output_buffer)
+    }
+}
+
+/// Byte budget shared by every stream of one connection.
+/// Tracks bytes that have been read off the network but not yet decoded into frames.
+#[derive(Clone)]
+pub struct ReadBudget {
+    buffered: Arc<AtomicUsize>,
+    budget: usize,
+}
+
+impl ReadBudget {
+    pub fn new(budget: usize) -> Self {
+        Self { buffered: Arc::new(AtomicUsize::new(0)), budget }
+    }
+}
+
+/// Decoder wrapper that charges its undecoded buffer to a connection-wide `ReadBudget`.
+/// The charge is released when the decoder is dropped.
+pub struct BudgetedDecoder<D> {
+    inner: D,
+    read_budget: ReadBudget,
+    charged: usize,
+}
+
+impl<D> BudgetedDecoder<D> {
+    pub fn new(inner: D, read_budget: ReadBudget) -> Self {
+        Self { inner, read_budget, charged: 0 }
+    }
+
+    fn recharge(&mut self, buffered: usize) -> Result<(), CodecError> {
+        let shared = &self.read_budget.buffered;
+        if buffered <= self.charged {
+            shared.fetch_sub(self.charged - buffered, Ordering::Relaxed);
+            self.charged = buffered;
+            return Ok(());
+        }
+
+        let increase = buffered - self.charged;
+        let total = shared.fetch_add(increase, Ordering::Relaxed) + increase;
+        self.charged = buffered;
+        if total > self.read_budget.budget {
+            return Err(CodecError::BufferExhausted {
+                buffered: total,
+                budget: self.read_budget.budget,
+            });
+        }
         Ok(())
     }
 }
 
+impl<D> Decoder for BudgetedDecoder<D>
+where
+    D: Decoder,
+    D::Error: From<CodecError>,
+{
+    type Item = D::Item;
+    type Error = D::Error;
+
+    fn decode(&mut self, incoming_bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let frame = self.inner.decode(incoming_bytes)?;
+        self.recharge(incoming_bytes.len())?;
+        Ok(frame)
+    }
+}
+
+impl<D> Drop for BudgetedDecoder<D> {
+    fn drop(&mut self) {
+        self.read_budget.buffered.fetch_sub(self.charged, Ordering::Relaxed);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
 
+    use futures_util::SinkExt;
     use prost::Message;
     use tokio_stream::StreamExt;
-    use tokio_util::codec::FramedRead;
+    use tokio_util::codec::{Framed, FramedRead};
 
     use super::*;
+    use crate::topic::TopicLimits;
+
+    /// Field-by-field view of a decoded message, used to report exactly which field differs.
+    /// `assert_message_eq` still compares whole messages, so a field missing here cannot hide
+    /// a difference.
+    trait MessageFields {
+        fn fields(&self) -> Vec<(&'static str, String)>;
+    }
+
+    fn render_bytes(bytes: &[u8]) -> String {
+        format!("{:?}", Bytes::copy_from_slice(bytes))
+    }
+
+    impl MessageFields for pb::Publish {
+        fn fields(&self) -> Vec<(&'static str, String)> {
+            vec![
+                ("topic", render_bytes(&self.topic)),
+                ("header", render_bytes(&self.header)),
+                ("payload", render_bytes(&self.payload)),
+                ("publish_id", self.publish_id.to_string()),
+                ("reply_to", format!("{:?}", self.reply_to.as_deref().map(render_bytes))),
+                ("retain", self.retain.to_string()),
+                ("topic_alias", format!("{:?}", self.topic_alias)),
+                ("fragment", format!("{:?}", self.fragment)),
+                ("qos", format!("{:?}", self.qos)),
+                ("priority", format!("{:?}", self.priority)),
+            ]
+        }
+    }
+
+    impl MessageFields for pb::Message {
+        fn fields(&self) -> Vec<(&'static str, String)> {
+            vec![
+                ("topic", render_bytes(&self.topic)),
+                ("subscription_id", self.subscription_id.to_string()),
+                ("header", render_bytes(&self.header)),
+                ("payload", render_bytes(&self.payload)),
+                ("reply_to", format!("{:?}", self.reply_to.as_deref().map(render_bytes))),
+                ("sequence", format!("{:?}", self.sequence)),
+                ("retained", self.retained.to_string()),
+                ("fragment", format!("{:?}", self.fragment)),
+                ("priority", format!("{:?}", self.priority)),
+                ("received_at_ms", format!("{:?}", self.received_at_ms)),
+                ("delivery_id", format!("{:?}", self.delivery_id)),
+            ]
+        }
+    }
+
+    #[track_caller]
+    fn assert_message_eq<T: MessageFields + PartialEq + std::fmt::Debug>(expected: &T, actual: &T) {
+        let differences: Vec<String> = expected
+            .fields()
+            .into_iter()
+            .zip(actual.fields())
+            .filter(|((_, expected_value), (_, actual_value))| expected_value != actual_value)
+            .map(|((field, expected_value), (_, actual_value))| {
+                format!("  {field}:\n    expected: {expected_value}\n    actual:   {actual_value}")
+            })
+            .collect();
+        assert!(differences.is_empty(), "messages differ:\n{}", differences.join("\n"));
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "header:\n    expected: b\"encoding:utf-8\"\n    actual:   b\"encoding:ascii\""
+    )]
+    fn assert_message_eq_pinpoints_differing_header() {
+        let expected =
+            pb::Message { header: Bytes::from_static(b"encoding:utf-8"), ..Default::default() };
+        let actual =
+            pb::Message { header: Bytes::from_static(b"encoding:ascii"), ..Default::default() };
+        assert_message_eq(&expected, &actual);
+    }
 
     #[test]
     fn encode_info_frame_has_header_and_payload() {
@@ -389,8 +1074,17 @@ mod tests {
             client_id: 0,
             requires_auth: false,
             tls_verify: false,
+            max_keepalive_secs: 0,
+            nonce: Vec::new(),
+            topic_alias_maximum: 0,
+            max_reassembled_payload: 0,
+            connect_urls: Vec::new(),
+            max_topic_length: None,
+            max_topic_layers: None,
+            max_header_bytes: None,
+            max_subscriptions: None,
         };
-        let mut codec = ServerCodec;
+        let mut codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         codec.encode(info.clone(), &mut output_buffer).unwrap();
@@ -415,6 +1109,12 @@ mod tests {
             verbose: true,
             auth_method: pb::AuthMethod::NoAuth as i32,
             credentials: None,
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
         };
         let payload = conn.encode_to_vec();
 
@@ -425,7 +1125,7 @@ mod tests {
         incoming_bytes.put_u32(payload.len() as u32);
         incoming_bytes.extend_from_slice(&payload);
 
-        let mut codec = ServerCodec;
+        let mut codec = ServerCodec::default();
         let decoded = codec.decode(&mut incoming_bytes).unwrap().unwrap();
         assert!(matches!(decoded, Frame::Connect(_)));
         assert!(incoming_bytes.is_empty());
@@ -441,9 +1141,18 @@ mod tests {
             client_id: 0,
             requires_auth: false,
             tls_verify: false,
+            max_keepalive_secs: 30,
+            nonce: Vec::new(),
+            topic_alias_maximum: 0,
+            max_reassembled_payload: 0,
+            connect_urls: Vec::new(),
+            max_topic_length: None,
+            max_topic_layers: None,
+            max_header_bytes: None,
+            max_subscriptions: None,
         };
-        let mut server_codec = ServerCodec;
-        let mut client_codec = ClientCodec;
+        let mut server_codec = ServerCodec::default();
+        let mut client_codec = ClientCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(info.clone(), &mut output_buffer).unwrap();
@@ -454,20 +1163,64 @@ mod tests {
                 assert_eq!(message.server_id, info.server_id);
                 assert_eq!(message.max_payload, info.max_payload);
             }
-            ClientFrame::Message(_) => panic!("unexpected Message frame"),
+            other => panic!("unexpected frame: {other:?}"),
         }
         assert!(output_buffer.is_empty());
     }
 
     #[test]
-    fn client_encode_connect_frame_has_header_and_payload() {
-        let conn = pb::Connect {
-            version: 1,
-            verbose: true,
+    fn info_with_long_server_id_roundtrips_without_truncation() {
+        const LONG_SERVER_ID_LENGTH: usize = 300;
+        let info = pb::Info {
+            server_id: "s".repeat(LONG_SERVER_ID_LENGTH),
+            ..ServerOutbound::default_info()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(info.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Info(info)));
+    }
+
+    #[test]
+    fn info_advertises_enforced_topic_limits() {
+        let info = ServerOutbound::default_info();
+
+        let limits = TopicLimits::default();
+        assert_eq!(info.max_topic_length, Some(limits.max_length as u32));
+        assert_eq!(info.max_topic_layers, Some(limits.max_layers as u32));
+    }
+
+    #[test]
+    fn info_with_connect_urls_roundtrips_in_order() {
+        let info = pb::Info {
+            connect_urls: vec!["broker-a:4433".to_string(), "broker-b:4433".to_string()],
+            ..ServerOutbound::default_info()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(info.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Info(info)));
+    }
+
+    #[test]
+    fn client_encode_connect_frame_has_header_and_payload() {
+        let conn = pb::Connect {
+            version: 1,
+            verbose: true,
             auth_method: pb::AuthMethod::NoAuth as i32,
             credentials: None,
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
         };
-        let mut codec = ClientCodec;
+        let mut codec = ClientCodec::default();
         let mut output_buffer = BytesMut::new();
 
         codec.encode(conn.clone(), &mut output_buffer).unwrap();
@@ -494,6 +1247,15 @@ mod tests {
             client_id: 0,
             requires_auth: false,
             tls_verify: false,
+            max_keepalive_secs: 0,
+            nonce: Vec::new(),
+            topic_alias_maximum: 0,
+            max_reassembled_payload: 0,
+            connect_urls: Vec::new(),
+            max_topic_length: None,
+            max_topic_layers: None,
+            max_header_bytes: None,
+            max_subscriptions: None,
         };
         let payload = info.encode_to_vec();
 
@@ -504,13 +1266,13 @@ mod tests {
         incoming_bytes.put_u32(payload.len() as u32);
         incoming_bytes.extend_from_slice(&payload);
 
-        let mut codec = ClientCodec;
+        let mut codec = ClientCodec::default();
         let decoded = codec.decode(&mut incoming_bytes).unwrap().unwrap();
         match decoded {
             ClientFrame::Info(message) => {
                 assert_eq!(message.server_id, info.server_id);
             }
-            ClientFrame::Message(_) => panic!("unexpected Message frame"),
+            other => panic!("unexpected frame: {other:?}"),
         }
         assert!(incoming_bytes.is_empty());
     }
@@ -525,9 +1287,18 @@ mod tests {
             client_id: 0,
             requires_auth: false,
             tls_verify: false,
+            max_keepalive_secs: 0,
+            nonce: Vec::new(),
+            topic_alias_maximum: 0,
+            max_reassembled_payload: 0,
+            connect_urls: Vec::new(),
+            max_topic_length: None,
+            max_topic_layers: None,
+            max_header_bytes: None,
+            max_subscriptions: None,
         };
-        let mut client_codec = ClientCodec;
-        let mut server_codec = ServerCodec;
+        let mut client_codec = ClientCodec::default();
+        let mut server_codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(info.clone(), &mut output_buffer).unwrap();
@@ -538,7 +1309,7 @@ mod tests {
                 assert_eq!(message.server_id, info.server_id);
                 assert_eq!(message.max_payload, info.max_payload);
             }
-            ClientFrame::Message(_) => panic!("unexpected Message frame"),
+            other => panic!("unexpected frame: {other:?}"),
         }
         assert!(output_buffer.is_empty());
     }
@@ -549,8 +1320,14 @@ mod tests {
             verbose: false,
             auth_method: pb::AuthMethod::NoAuth as i32,
             credentials: None,
+            max_payload: None,
+            will: None,
+            keepalive_secs: None,
+            client_name: None,
+            receive_timestamps: false,
+            no_echo: false,
         };
-        let mut codec = ClientCodec;
+        let mut codec = ClientCodec::default();
         let mut buf = BytesMut::new();
         codec.encode(conn, &mut buf).unwrap();
         buf.to_vec()
@@ -560,7 +1337,7 @@ mod tests {
     async fn framed_read_decodes_single_connect_frame() {
         let data = build_connect_frame();
         let cursor = Cursor::new(data);
-        let mut framed = FramedRead::with_capacity(cursor, ServerCodec, 32 * 1024);
+        let mut framed = FramedRead::with_capacity(cursor, ServerCodec::default(), 32 * 1024);
 
         let frame = framed.next().await.unwrap().unwrap();
         assert!(matches!(frame, Frame::Connect(_)));
@@ -572,7 +1349,7 @@ mod tests {
         let mut data = build_connect_frame();
         data.extend(build_connect_frame());
         let cursor = Cursor::new(data);
-        let mut framed = FramedRead::with_capacity(cursor, ServerCodec, 32 * 1024);
+        let mut framed = FramedRead::with_capacity(cursor, ServerCodec::default(), 32 * 1024);
 
         let frame1 = framed.next().await.unwrap().unwrap();
         assert!(matches!(frame1, Frame::Connect(_)));
@@ -589,43 +1366,523 @@ mod tests {
         let mut data = vec![0xFF]; // invalid command byte
         data.extend(conn_data);
         let cursor = Cursor::new(data);
-        let mut framed = FramedRead::with_capacity(cursor, ServerCodec, 32 * 1024);
+        let mut framed = FramedRead::with_capacity(cursor, ServerCodec::default(), 32 * 1024);
 
         let frame = framed.next().await.unwrap().unwrap();
         assert!(matches!(frame, Frame::Connect(_)));
         assert!(framed.next().await.is_none());
     }
 
+    // --- Command ---
+
+    #[test]
+    fn inbound_frames_report_client_to_server_commands() {
+        let frames = [
+            (Frame::Connect(pb::Connect::default()), Command::Connect),
+            (Frame::Publish(pb::Publish::default()), Command::Publish),
+            (Frame::Subscribe(pb::Subscribe::default()), Command::Subscribe),
+            (Frame::UnSubscribe(pb::UnSubscribe::default()), Command::UnSubscribe),
+            (Frame::MessageAck(pb::MessageAck::default()), Command::MessageAck),
+            (Frame::Ping(pb::Ping::default()), Command::Ping),
+            (Frame::Disconnect(pb::Disconnect::default()), Command::Disconnect),
+            (Frame::Extension(pb::Extension::default()), Command::Extension),
+        ];
+
+        for (frame, command) in frames {
+            assert_eq!(frame.command(), command);
+            assert!(command.is_client_to_server(), "{command:?}");
+        }
+    }
+
+    #[test]
+    fn outbound_frames_report_server_to_client_commands() {
+        let frames = [
+            (ClientFrame::Info(pb::Info::default()), Command::Info),
+            (ClientFrame::Message(pb::Message::default()), Command::Message),
+            (ClientFrame::Ack(pb::Ack::default()), Command::Ack),
+            (ClientFrame::Error(pb::Error::default()), Command::Error),
+            (ClientFrame::Pong(pb::Pong::default()), Command::Pong),
+            (ClientFrame::SubAck(pb::SubAck::default()), Command::SubAck),
+            (ClientFrame::Disconnect(pb::Disconnect::default()), Command::Disconnect),
+            (ClientFrame::Extension(pb::Extension::default()), Command::Extension),
+        ];
+
+        for (frame, command) in frames {
+            assert_eq!(frame.command(), command);
+            assert!(command.is_server_to_client(), "{command:?}");
+        }
+    }
+
+    // --- Connect ---
+
+    #[tokio::test]
+    async fn framed_duplex_delivers_connect() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let mut client = Framed::new(client_io, ClientCodec::default());
+        let mut server = Framed::new(server_io, ServerCodec::default());
+        let connect = ClientOutbound::connect(PROTOCOL_VERSION, false);
+
+        client.send(connect.clone()).await.unwrap();
+
+        let frame = server.next().await.unwrap().unwrap();
+        assert_eq!(frame, Frame::Connect(connect));
+    }
+
+    #[test]
+    fn connect_with_max_payload_roundtrips() {
+        let connect = pb::Connect { max_payload: Some(512), ..ClientOutbound::connect(1, false) };
+        let mut client_codec = ClientCodec::default();
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        client_codec.encode(connect, &mut output_buffer).unwrap();
+
+        let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
+        let Frame::Connect(message) = decoded else { panic!("expected Connect frame") };
+        assert_eq!(message.max_payload, Some(512));
+    }
+
+    #[test]
+    fn connect_with_client_name_roundtrips() {
+        let connect = pb::Connect {
+            client_name: Some("billing-worker".to_string()),
+            ..ClientOutbound::connect(1, false)
+        };
+        let mut output_buffer = BytesMut::new();
+
+        ClientCodec::default().encode(connect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(Frame::Connect(connect)));
+    }
+
+    #[test]
+    fn connect_with_will_roundtrips() {
+        let will = pb::Will {
+            topic: b"clients/42/status".to_vec(),
+            payload: b"offline".to_vec(),
+            header: b"content-type:text/plain".to_vec(),
+            retain: true,
+        };
+        let connect = pb::Connect { will: Some(will), ..ClientOutbound::connect(1, false) };
+        let mut output_buffer = BytesMut::new();
+
+        ClientCodec::default().encode(connect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(Frame::Connect(connect)));
+    }
+
+    #[test]
+    fn connect_with_token_roundtrips() {
+        let connect = ClientOutbound::connect_with_token(1, false, b"api-key".to_vec());
+        let mut output_buffer = BytesMut::new();
+
+        ClientCodec::default().encode(connect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(Frame::Connect(connect)));
+    }
+
+    #[test]
+    fn connect_with_challenge_roundtrips() {
+        let connect =
+            ClientOutbound::connect_with_challenge(1, false, b"key".to_vec(), b"sig".to_vec());
+        let mut client_codec = ClientCodec::default();
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        client_codec.encode(connect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
+        assert_eq!(decoded, Frame::Connect(connect));
+    }
+
+    #[test]
+    fn truncated_challenge_payload_is_decode_error() {
+        let connect =
+            ClientOutbound::connect_with_challenge(1, false, b"key".to_vec(), b"sig".to_vec());
+        let mut payload = connect.encode_to_vec();
+        payload.pop();
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Connect as u8);
+        incoming_bytes.put_u32(payload.len() as u32);
+        incoming_bytes.extend_from_slice(&payload);
+
+        let error = ServerCodec::default().decode(&mut incoming_bytes).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ServerCodecError::Codec(CodecError::DecodePayload { command: Command::Connect, .. })
+        ));
+    }
+
+    #[test]
+    fn decode_error_names_command_and_field() {
+        // Field 1 (topic) sent with the varint wire type instead of length-delimited.
+        const MISTYPED_TOPIC: [u8; 2] = [0x08, 0x01];
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Publish as u8);
+        incoming_bytes.put_u32(MISTYPED_TOPIC.len() as u32);
+        incoming_bytes.extend_from_slice(&MISTYPED_TOPIC);
+
+        let error = ServerCodec::default().decode(&mut incoming_bytes).unwrap_err();
+
+        let message = error.to_string();
+        assert!(message.contains("Publish payload"), "{message}");
+        assert!(message.contains("Publish.topic"), "{message}");
+    }
+
+    #[test]
+    fn connect_with_unknown_auth_method_is_rejected() {
+        const UNKNOWN_AUTH_METHOD: i32 = 99;
+        let connect =
+            pb::Connect { auth_method: UNKNOWN_AUTH_METHOD, ..ClientOutbound::connect(1, false) };
+        let mut output_buffer = BytesMut::new();
+        ClientCodec::default().encode(connect, &mut output_buffer).unwrap();
+
+        let error = ServerCodec::default().decode(&mut output_buffer).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ServerCodecError::Codec(CodecError::UnknownAuthMethod(UNKNOWN_AUTH_METHOD))
+        ));
+    }
+
     // --- Publish ---
 
+    #[test]
+    fn encode_frame_matches_header_plus_encoded_payload() {
+        const PAYLOAD_SIZES: [usize; 4] = [0, 1, 127, 64 * 1024];
+        for size in PAYLOAD_SIZES {
+            let publish = pb::Publish {
+                topic: Bytes::from_static(b"a/b"),
+                payload: Bytes::from(vec![0xAB; size]),
+                ..Default::default()
+            };
+            let payload = publish.encode_payload().unwrap();
+            let mut expected = BytesMut::new();
+            expected.put_u8(Command::Publish as u8);
+            expected.put_u32(payload.len() as u32);
+            expected.extend_from_slice(&payload);
+            let mut output_buffer = BytesMut::new();
+
+            encode_frame(&publish, &mut output_buffer).unwrap();
+
+            assert_eq!(output_buffer, expected, "payload size {size}");
+        }
+    }
+
+    #[test]
+    fn decoded_publish_payload_borrows_frame_buffer() {
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            payload: Bytes::from(vec![0; 1024]),
+            ..Default::default()
+        };
+        let mut output_buffer = BytesMut::new();
+        encode_frame(&publish, &mut output_buffer).unwrap();
+        let frame_range = output_buffer.as_ptr_range();
+
+        let Some(Frame::Publish(decoded)) =
+            ServerCodec::default().decode(&mut output_buffer).unwrap()
+        else {
+            panic!("expected a Publish frame");
+        };
+
+        assert!(frame_range.contains(&decoded.payload.as_ptr()));
+    }
+
+    #[test]
+    fn server_side_decodes_only_client_to_server_commands() {
+        let commands: Vec<Command> =
+            ServerSide::DECODERS.iter().map(|(command, _)| *command).collect();
+
+        assert!(commands.iter().all(|command| command.is_client_to_server()));
+    }
+
+    #[test]
+    fn client_side_decodes_only_server_to_client_commands() {
+        let commands: Vec<Command> =
+            ClientSide::DECODERS.iter().map(|(command, _)| *command).collect();
+
+        assert!(commands.iter().all(|command| command.is_server_to_client()));
+    }
+
+    #[test]
+    fn encode_into_appends_frames_to_one_buffer() {
+        let first = pb::Publish { topic: Bytes::from_static(b"a/1"), ..Default::default() };
+        let second = pb::Publish { topic: Bytes::from_static(b"a/2"), ..Default::default() };
+        let mut output_buffer = BytesMut::with_capacity(first.frame_len() + second.frame_len());
+
+        let client_codec = ClientCodec::default();
+        client_codec.encode_into(&first, &mut output_buffer).unwrap();
+        client_codec.encode_into(&second, &mut output_buffer).unwrap();
+
+        let mut codec = ServerCodec::default();
+        assert_eq!(codec.decode(&mut output_buffer).unwrap(), Some(Frame::Publish(first)));
+        assert_eq!(codec.decode(&mut output_buffer).unwrap(), Some(Frame::Publish(second)));
+    }
+
+    #[test]
+    fn client_encode_rejects_publish_above_max_payload() {
+        const ADVERTISED_MAX_PAYLOAD: usize = 8;
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            payload: Bytes::from(vec![0; ADVERTISED_MAX_PAYLOAD + 1]),
+            ..Default::default()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        let result = ClientCodec::default()
+            .with_max_payload(ADVERTISED_MAX_PAYLOAD)
+            .encode(publish, &mut output_buffer);
+
+        assert!(matches!(
+            result,
+            Err(ClientCodecError::Codec(CodecError::PayloadTooLarge {
+                max: ADVERTISED_MAX_PAYLOAD,
+                ..
+            }))
+        ));
+        assert!(output_buffer.is_empty());
+    }
+
+    #[test]
+    fn server_encode_rejects_message_above_max_payload() {
+        const MAX_PAYLOAD: usize = 8;
+        let message = pb::Message {
+            topic: Bytes::from_static(b"a/b"),
+            header: Bytes::from(vec![0; MAX_PAYLOAD]),
+            payload: Bytes::from(vec![0; 1]),
+            ..Default::default()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        let result = ServerCodec::default()
+            .with_max_payload(MAX_PAYLOAD)
+            .encode(message, &mut output_buffer);
+
+        assert!(matches!(result, Err(ServerCodecError::Codec(CodecError::PayloadTooLarge { .. }))));
+    }
+
+    #[test]
+    fn frame_len_matches_encoded_frame_size() {
+        const PAYLOAD_SIZES: [usize; 3] = [0, 127, 64 * 1024];
+        for size in PAYLOAD_SIZES {
+            let publish = pb::Publish {
+                topic: Bytes::from_static(b"a/b"),
+                payload: Bytes::from(vec![0xAB; size]),
+                ..Default::default()
+            };
+            let mut output_buffer = BytesMut::new();
+
+            encode_frame(&publish, &mut output_buffer).unwrap();
+
+            assert_eq!(output_buffer.len(), publish.frame_len(), "payload size {size}");
+        }
+    }
+
     #[test]
     fn encode_and_decode_publish_frame() {
         let publish = pb::Publish {
-            topic: b"sensors/temperature".to_vec(),
-            payload: b"42.5".to_vec(),
-            header: b"content-type:text/plain".to_vec(),
+            topic: Bytes::from_static(b"sensors/temperature"),
+            payload: Bytes::from_static(b"42.5"),
+            header: Bytes::from_static(b"content-type:text/plain"),
+            publish_id: 0,
+            reply_to: Some(Bytes::from_static(b"_INBOX/sensors")),
+            retain: true,
+            topic_alias: None,
+            fragment: None,
+            qos: pb::QosLevel::AtMostOnce as i32,
+            priority: None,
         };
-        let mut server_codec = ServerCodec;
+        let mut server_codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(publish.clone(), &mut output_buffer).unwrap();
 
         let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
         let Frame::Publish(message) = decoded else { panic!("expected Publish frame") };
-        assert_eq!(message.topic, publish.topic);
-        assert_eq!(message.payload, publish.payload);
-        assert_eq!(message.header, publish.header);
+        assert_message_eq(&publish, &message);
         assert!(output_buffer.is_empty());
     }
 
+    #[test]
+    fn publish_at_payload_limit_decodes() {
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"blobs"),
+            payload: Bytes::from(vec![0; MAXIMUM_PAYLOAD_BYTES]),
+            ..Default::default()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(publish, &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert!(matches!(decoded, Some(Frame::Publish(_))));
+    }
+
+    #[test]
+    fn publish_above_payload_limit_is_rejected() {
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"blobs"),
+            payload: Bytes::from(vec![0; MAXIMUM_PAYLOAD_BYTES + 1]),
+            ..Default::default()
+        };
+        let mut output_buffer = BytesMut::new();
+
+        // The codecs refuse to encode this, so write the frame directly.
+        encode_frame(&publish, &mut output_buffer).unwrap();
+
+        let result = ServerCodec::default().decode(&mut output_buffer);
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::PayloadTooLarge {
+                max: MAXIMUM_PAYLOAD_BYTES,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn publish_header_above_configured_limit_is_rejected() {
+        const MAX_HEADER_BYTES: usize = 8;
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            header: Bytes::from(vec![b'h'; MAX_HEADER_BYTES + 1]),
+            ..Default::default()
+        };
+        let mut codec = ServerCodec::default().with_max_header_bytes(MAX_HEADER_BYTES);
+        let mut output_buffer = BytesMut::new();
+        codec.encode(publish, &mut output_buffer).unwrap();
+
+        let result = codec.decode(&mut output_buffer);
+
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::HeadersTooLarge { max: MAX_HEADER_BYTES, .. }))
+        ));
+    }
+
+    #[test]
+    fn publish_batch_decodes_as_individual_publishes_in_order() {
+        let publishes: Vec<_> = ["a/1", "a/2", "a/3"]
+            .into_iter()
+            .map(|topic| pb::Publish {
+                topic: Bytes::copy_from_slice(topic.as_bytes()),
+                ..Default::default()
+            })
+            .collect();
+        let mut codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+        codec
+            .encode(pb::PublishBatch { publishes: publishes.clone() }, &mut output_buffer)
+            .unwrap();
+
+        let mut decoded = Vec::new();
+        while let Some(Frame::Publish(publish)) = codec.decode(&mut output_buffer).unwrap() {
+            decoded.push(publish);
+        }
+
+        assert_eq!(decoded, publishes);
+    }
+
+    #[test]
+    fn empty_publish_batch_is_skipped() {
+        let ping = pb::Ping {};
+        let mut codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+        codec.encode(pb::PublishBatch::default(), &mut output_buffer).unwrap();
+        codec.encode(ping, &mut output_buffer).unwrap();
+
+        let decoded = codec.decode(&mut output_buffer).unwrap();
+
+        assert_eq!(decoded, Some(Frame::Ping(ping)));
+    }
+
+    #[test]
+    fn publish_batch_with_oversized_entry_is_rejected() {
+        let oversized = pb::Publish {
+            topic: Bytes::from_static(b"blobs"),
+            payload: Bytes::from(vec![0; MAXIMUM_PAYLOAD_BYTES + 1]),
+            ..Default::default()
+        };
+        let mut codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+        encode_frame(&pb::PublishBatch { publishes: vec![oversized] }, &mut output_buffer).unwrap();
+
+        let result = codec.decode(&mut output_buffer);
+
+        assert!(matches!(result, Err(ServerCodecError::Codec(CodecError::PayloadTooLarge { .. }))));
+    }
+
+    #[test]
+    fn publish_batch_with_too_many_entries_is_rejected() {
+        let batch =
+            pb::PublishBatch { publishes: vec![pb::Publish::default(); MAX_BATCH_ENTRIES + 1] };
+        let mut codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+        encode_frame(&batch, &mut output_buffer).unwrap();
+
+        let result = codec.decode(&mut output_buffer);
+
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::TooManyBatchEntries { .. }))
+        ));
+    }
+
+    #[test]
+    fn frame_declaring_oversized_payload_is_rejected_before_buffering() {
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Publish as u8);
+        incoming_bytes.put_u32(MAXIMUM_FRAME_PAYLOAD_BYTES as u32 + 1);
+
+        let result = ServerCodec::default().decode(&mut incoming_bytes);
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::FrameTooLarge {
+                max_payload: MAXIMUM_FRAME_PAYLOAD_BYTES,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn frame_above_configured_limit_is_rejected_from_header() {
+        const MAX_FRAME_PAYLOAD: usize = 64;
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Publish as u8);
+        incoming_bytes.put_u32(MAX_FRAME_PAYLOAD as u32 + 1);
+        let mut codec = ServerCodec::default().with_max_frame_payload(MAX_FRAME_PAYLOAD);
+
+        let result = codec.decode(&mut incoming_bytes);
+
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::FrameTooLarge {
+                max_payload: MAX_FRAME_PAYLOAD,
+                ..
+            }))
+        ));
+    }
+
     #[test]
     fn encode_publish_frame_has_correct_header() {
         let publish = pb::Publish {
-            topic: b"test/topic".to_vec(),
-            payload: b"hello".to_vec(),
-            header: vec![],
+            topic: Bytes::from_static(b"test/topic"),
+            payload: Bytes::from_static(b"hello"),
+            header: Bytes::from(vec![]),
+            publish_id: 0,
+            reply_to: None,
+            retain: false,
+            topic_alias: None,
+            fragment: None,
+            qos: pb::QosLevel::AtMostOnce as i32,
+            priority: None,
         };
-        let mut codec = ServerCodec;
+        let mut codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         codec.encode(publish, &mut output_buffer).unwrap();
@@ -645,9 +1902,11 @@ mod tests {
         let subscribe = pb::Subscribe {
             topic: b"sensors/#".to_vec(),
             subscription_id: 7,
-            queue_group: "workers".to_string(),
+            queue_group: Some("workers".to_string()),
+            qos: pb::QosLevel::AtMostOnce as i32,
+            max_msgs: None,
         };
-        let mut server_codec = ServerCodec;
+        let mut server_codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(subscribe.clone(), &mut output_buffer).unwrap();
@@ -660,29 +1919,146 @@ mod tests {
         assert!(output_buffer.is_empty());
     }
 
+    #[test]
+    fn subscribe_qos_roundtrips() {
+        let subscribe = pb::Subscribe {
+            topic: b"sensors/#".to_vec(),
+            subscription_id: 8,
+            queue_group: None,
+            qos: pb::QosLevel::AtLeastOnce as i32,
+            max_msgs: None,
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(subscribe, &mut output_buffer).unwrap();
+
+        let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
+        let Frame::Subscribe(message) = decoded else { panic!("expected Subscribe frame") };
+        assert_eq!(message.qos(), pb::QosLevel::AtLeastOnce);
+    }
+
+    #[test]
+    fn subscribe_with_unknown_qos_is_rejected() {
+        const UNKNOWN_QOS: i32 = 3;
+        let subscribe = pb::Subscribe {
+            topic: b"jobs".to_vec(),
+            subscription_id: 2,
+            queue_group: None,
+            qos: UNKNOWN_QOS,
+            max_msgs: None,
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(subscribe, &mut output_buffer).unwrap();
+
+        let error = server_codec.decode(&mut output_buffer).unwrap_err();
+        assert!(matches!(error, ServerCodecError::Codec(CodecError::UnknownQos(UNKNOWN_QOS))));
+    }
+
+    #[test]
+    fn publish_with_priority_above_maximum_is_rejected() {
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"jobs"),
+            priority: Some(MAX_PRIORITY + 1),
+            ..Default::default()
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(publish, &mut output_buffer).unwrap();
+
+        let error = server_codec.decode(&mut output_buffer).unwrap_err();
+        assert!(matches!(
+            error,
+            ServerCodecError::Codec(CodecError::InvalidPriority(priority))
+                if priority == MAX_PRIORITY + 1
+        ));
+    }
+
+    #[test]
+    fn publish_with_unknown_qos_is_rejected() {
+        const UNKNOWN_QOS: i32 = 3;
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"jobs"),
+            qos: UNKNOWN_QOS,
+            ..Default::default()
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(publish, &mut output_buffer).unwrap();
+
+        let error = server_codec.decode(&mut output_buffer).unwrap_err();
+        assert!(matches!(error, ServerCodecError::Codec(CodecError::UnknownQos(UNKNOWN_QOS))));
+    }
+
     #[test]
     fn subscribe_without_queue_group_roundtrips() {
         let subscribe = pb::Subscribe {
             topic: b"events/+/status".to_vec(),
             subscription_id: 1,
-            queue_group: String::new(),
+            queue_group: None,
+            qos: pb::QosLevel::AtMostOnce as i32,
+            max_msgs: None,
         };
-        let mut server_codec = ServerCodec;
+        let mut server_codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(subscribe.clone(), &mut output_buffer).unwrap();
         let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
         let Frame::Subscribe(message) = decoded else { panic!("expected Subscribe frame") };
         assert_eq!(message.subscription_id, subscribe.subscription_id);
-        assert!(message.queue_group.is_empty());
+        assert_eq!(message.queue_group_str(), None);
+    }
+
+    #[test]
+    fn subscribe_with_empty_queue_group_is_rejected() {
+        let subscribe = pb::Subscribe {
+            topic: b"jobs".to_vec(),
+            subscription_id: 2,
+            queue_group: Some(String::new()),
+            qos: pb::QosLevel::AtMostOnce as i32,
+            max_msgs: None,
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(subscribe, &mut output_buffer).unwrap();
+
+        let error = server_codec.decode(&mut output_buffer).unwrap_err();
+        assert!(matches!(
+            error,
+            ServerCodecError::Codec(CodecError::InvalidQueueGroup { length: 0 })
+        ));
+    }
+
+    #[test]
+    fn subscribe_with_queue_group_exposes_group_name() {
+        let subscribe = pb::Subscribe {
+            topic: b"jobs".to_vec(),
+            subscription_id: 3,
+            queue_group: Some("workers".to_string()),
+            qos: pb::QosLevel::AtMostOnce as i32,
+            max_msgs: None,
+        };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(subscribe, &mut output_buffer).unwrap();
+
+        let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
+        let Frame::Subscribe(message) = decoded else { panic!("expected Subscribe frame") };
+        assert_eq!(message.queue_group_str(), Some("workers"));
     }
 
     // --- UnSubscribe ---
 
     #[test]
     fn encode_and_decode_unsubscribe_frame() {
-        let unsubscribe = pb::UnSubscribe { subscription_id: 42 };
-        let mut server_codec = ServerCodec;
+        let unsubscribe = pb::UnSubscribe { subscription_id: 42, max_msgs: None };
+        let mut server_codec = ServerCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(unsubscribe, &mut output_buffer).unwrap();
@@ -693,38 +2069,78 @@ mod tests {
         assert!(output_buffer.is_empty());
     }
 
+    // --- MessageAck ---
+
+    #[test]
+    fn encode_and_decode_message_ack_frame() {
+        let ack = pb::MessageAck { delivery_id: 17 };
+        let mut server_codec = ServerCodec::default();
+        let mut output_buffer = BytesMut::new();
+
+        server_codec.encode(ack, &mut output_buffer).unwrap();
+
+        let decoded = server_codec.decode(&mut output_buffer).unwrap().unwrap();
+        assert_eq!(decoded, Frame::MessageAck(ack));
+    }
+
     // --- Message ---
 
     #[test]
     fn encode_and_decode_message_frame() {
         let message = pb::Message {
-            topic: b"sensors/temperature".to_vec(),
+            topic: Bytes::from_static(b"sensors/temperature"),
             subscription_id: 3,
-            payload: b"23.1".to_vec(),
-            header: b"encoding:utf-8".to_vec(),
+            payload: Bytes::from_static(b"23.1"),
+            header: Bytes::from_static(b"encoding:utf-8"),
+            reply_to: Some(Bytes::from_static(b"_INBOX/sensors")),
+            sequence: Some(9),
+            retained: true,
+            fragment: None,
+            priority: None,
+            received_at_ms: None,
+            delivery_id: None,
         };
-        let mut server_codec = ServerCodec;
-        let mut client_codec = ClientCodec;
+        let mut server_codec = ServerCodec::default();
+        let mut client_codec = ClientCodec::default();
         let mut output_buffer = BytesMut::new();
 
         server_codec.encode(message.clone(), &mut output_buffer).unwrap();
 
         let decoded = client_codec.decode(&mut output_buffer).unwrap().unwrap();
         let ClientFrame::Message(delivered) = decoded else { panic!("expected Message frame") };
-        assert_eq!(delivered.topic, message.topic);
-        assert_eq!(delivered.subscription_id, message.subscription_id);
-        assert_eq!(delivered.payload, message.payload);
-        assert_eq!(delivered.header, message.header);
+        assert_message_eq(&message, &delivered);
         assert!(output_buffer.is_empty());
     }
 
+    #[test]
+    fn message_with_priority_above_maximum_is_rejected() {
+        let message = pb::Message { priority: Some(MAX_PRIORITY + 1), ..Default::default() };
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(message, &mut output_buffer).unwrap();
+
+        let error = ClientCodec::default().decode(&mut output_buffer).unwrap_err();
+        assert!(matches!(
+            error,
+            ClientCodecError::Codec(CodecError::InvalidPriority(priority))
+                if priority == MAX_PRIORITY + 1
+        ));
+    }
+
     #[test]
     fn client_decode_message_frame_recovers_from_bad_prefix() {
         let message = pb::Message {
-            topic: b"test/topic".to_vec(),
+            topic: Bytes::from_static(b"test/topic"),
             subscription_id: 5,
-            payload: b"data".to_vec(),
-            header: vec![],
+            payload: Bytes::from_static(b"data"),
+            header: Bytes::from(vec![]),
+            reply_to: None,
+            sequence: None,
+            retained: false,
+            fragment: None,
+            priority: None,
+            received_at_ms: None,
+            delivery_id: None,
         };
         let payload = message.encode_to_vec();
 
@@ -734,38 +2150,331 @@ mod tests {
         incoming_bytes.put_u32(payload.len() as u32);
         incoming_bytes.extend_from_slice(&payload);
 
-        let mut codec = ClientCodec;
+        let mut codec = ClientCodec::default();
         let decoded = codec.decode(&mut incoming_bytes).unwrap().unwrap();
         let ClientFrame::Message(delivered) = decoded else { panic!("expected Message frame") };
         assert_eq!(delivered.subscription_id, message.subscription_id);
         assert!(incoming_bytes.is_empty());
     }
 
+    #[test]
+    fn server_outbound_message_forwards_publish_with_receive_time() {
+        const RECEIVED_AT_MS: u64 = 1_700_000_000_000;
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"sensors/temp"),
+            payload: Bytes::from_static(b"23.1"),
+            priority: Some(MAX_PRIORITY),
+            ..Default::default()
+        };
+
+        let message = ServerOutbound::message(&publish, 4, RECEIVED_AT_MS);
+
+        let expected = pb::Message {
+            topic: publish.topic.clone(),
+            subscription_id: 4,
+            payload: publish.payload.clone(),
+            priority: Some(MAX_PRIORITY),
+            received_at_ms: Some(RECEIVED_AT_MS),
+            ..Default::default()
+        };
+        assert_message_eq(&expected, &message);
+    }
+
+    // --- Extension ---
+
+    #[test]
+    fn extension_decodes_in_both_directions() {
+        const SUB_COMMAND: u64 = 300;
+        let extension =
+            pb::Extension { command: SUB_COMMAND, payload: Bytes::from_static(b"opaque") };
+        let mut to_server = BytesMut::new();
+        let mut to_client = BytesMut::new();
+
+        ClientCodec::default().encode(extension.clone(), &mut to_server).unwrap();
+        ServerCodec::default().encode(extension.clone(), &mut to_client).unwrap();
+
+        let received_by_server = ServerCodec::default().decode(&mut to_server).unwrap();
+        let received_by_client = ClientCodec::default().decode(&mut to_client).unwrap();
+        assert_eq!(received_by_server, Some(Frame::Extension(extension.clone())));
+        assert_eq!(received_by_client, Some(ClientFrame::Extension(extension)));
+    }
+
+    #[test]
+    fn extension_payload_above_max_payload_is_rejected() {
+        const MAX_PAYLOAD: usize = 4;
+        let extension = pb::Extension { command: 1, payload: Bytes::from_static(b"oversized") };
+        let mut output_buffer = BytesMut::new();
+        encode_frame(&extension, &mut output_buffer).unwrap();
+
+        let error = ServerCodec::default()
+            .with_max_payload(MAX_PAYLOAD)
+            .decode(&mut output_buffer)
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ServerCodecError::Codec(CodecError::PayloadTooLarge { max: MAX_PAYLOAD, .. })
+        ));
+    }
+
+    // --- Ack / Error ---
+
+    #[test]
+    fn encode_and_decode_ack_frame() {
+        let ack = ServerOutbound::ack(7);
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(ack, &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Ack(ack)));
+    }
+
+    #[test]
+    fn decode_zero_length_ack_frame() {
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Ack as u8);
+        incoming_bytes.put_u32(0);
+
+        let decoded = ClientCodec::default().decode(&mut incoming_bytes).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Ack(ServerOutbound::ack(0))));
+    }
+
+    #[test]
+    fn encode_and_decode_error_frame_with_reason() {
+        let error =
+            ServerOutbound::error(7, ErrorCode::InvalidTopic, Some("invalid topic".to_string()));
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(error.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Error(error)));
+    }
+
+    #[test]
+    fn encode_and_decode_error_frame_without_reason() {
+        let error = ServerOutbound::error(0, ErrorCode::PayloadTooLarge, None);
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(error, &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap().unwrap();
+        let ClientFrame::Error(decoded) = decoded else { panic!("expected Error frame") };
+        assert_eq!(decoded.error_code().unwrap(), ErrorCode::PayloadTooLarge);
+        assert_eq!(decoded.reason, None);
+    }
+
+    #[test]
+    fn fatal_error_frame_roundtrips_fatal_flag() {
+        let error = ServerOutbound::fatal_error(ErrorCode::SlowConsumer, None);
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(error, &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap().unwrap();
+        let ClientFrame::Error(decoded) = decoded else { panic!("expected Error frame") };
+        assert!(decoded.fatal);
+    }
+
+    #[test]
+    fn error_with_connection_ending_code_is_fatal() {
+        let error = ServerOutbound::error(0, ErrorCode::SlowConsumer, None);
+
+        assert!(error.fatal);
+    }
+
+    #[test]
+    fn error_with_request_scoped_code_is_not_fatal() {
+        let error = ServerOutbound::error(7, ErrorCode::InvalidTopic, None);
+
+        assert!(!error.fatal);
+    }
+
+    #[test]
+    fn error_frame_with_unknown_code_is_reported() {
+        const UNKNOWN_CODE: u32 = 0xFF;
+        let error = pb::Error { publish_id: 0, code: UNKNOWN_CODE, reason: None, fatal: false };
+
+        assert!(matches!(error.error_code(), Err(CodecError::UnknownErrorCode(UNKNOWN_CODE))));
+    }
+
+    // --- SubAck ---
+
+    #[test]
+    fn denied_sub_ack_roundtrips_error_code() {
+        let sub_ack = ServerOutbound::sub_denied(4, ErrorCode::PermissionViolation);
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(sub_ack, &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::SubAck(sub_ack)));
+    }
+
+    // --- Disconnect ---
+
+    #[test]
+    fn server_disconnect_decodes_on_client() {
+        let disconnect = ServerOutbound::disconnect(
+            pb::DisconnectReason::ServerShutdown,
+            Some("maintenance".to_string()),
+        );
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(disconnect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Disconnect(disconnect)));
+    }
+
+    #[test]
+    fn client_disconnect_decodes_on_server() {
+        let disconnect = pb::Disconnect::default();
+        let mut output_buffer = BytesMut::new();
+
+        ClientCodec::default().encode(disconnect.clone(), &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(Frame::Disconnect(disconnect)));
+    }
+
+    // --- Ping / Pong ---
+
+    #[test]
+    fn client_encode_server_decode_ping() {
+        let mut output_buffer = BytesMut::new();
+
+        ClientCodec::default().encode(pb::Ping {}, &mut output_buffer).unwrap();
+
+        let decoded = ServerCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(Frame::Ping(pb::Ping {})));
+    }
+
+    #[test]
+    fn server_encode_client_decode_pong() {
+        let mut output_buffer = BytesMut::new();
+
+        ServerCodec::default().encode(pb::Pong {}, &mut output_buffer).unwrap();
+
+        let decoded = ClientCodec::default().decode(&mut output_buffer).unwrap();
+        assert_eq!(decoded, Some(ClientFrame::Pong(pb::Pong {})));
+    }
+
+    #[test]
+    fn decode_zero_length_ping_frame() {
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Ping as u8);
+        incoming_bytes.put_u32(0);
+
+        let decoded = ServerCodec::default().decode(&mut incoming_bytes).unwrap();
+        assert_eq!(decoded, Some(Frame::Ping(pb::Ping {})));
+        assert!(incoming_bytes.is_empty());
+    }
+
     // --- Mixed frame sequence ---
 
     #[tokio::test]
     async fn framed_read_decodes_publish_subscribe_unsubscribe_sequence() {
-        let publish =
-            pb::Publish { topic: b"a/b".to_vec(), payload: b"payload".to_vec(), header: vec![] };
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"a/b"),
+            payload: Bytes::from_static(b"payload"),
+            header: Bytes::from(vec![]),
+            publish_id: 0,
+            reply_to: None,
+            retain: false,
+            topic_alias: None,
+            fragment: None,
+            qos: pb::QosLevel::AtMostOnce as i32,
+            priority: None,
+        };
         let subscribe = pb::Subscribe {
             topic: b"a/#".to_vec(),
             subscription_id: 1,
-            queue_group: String::new(),
+            queue_group: None,
+            qos: pb::QosLevel::AtMostOnce as i32,
+            max_msgs: None,
         };
-        let unsubscribe = pb::UnSubscribe { subscription_id: 1 };
+        let unsubscribe = pb::UnSubscribe { subscription_id: 1, max_msgs: None };
 
-        let mut client_codec = ClientCodec;
+        let mut client_codec = ClientCodec::default();
         let mut buf = BytesMut::new();
         client_codec.encode(publish, &mut buf).unwrap();
         client_codec.encode(subscribe, &mut buf).unwrap();
         client_codec.encode(unsubscribe, &mut buf).unwrap();
 
         let cursor = Cursor::new(buf.to_vec());
-        let mut framed = FramedRead::with_capacity(cursor, ServerCodec, 32 * 1024);
+        let mut framed = FramedRead::with_capacity(cursor, ServerCodec::default(), 32 * 1024);
 
         assert!(matches!(framed.next().await.unwrap().unwrap(), Frame::Publish(_)));
         assert!(matches!(framed.next().await.unwrap().unwrap(), Frame::Subscribe(_)));
         assert!(matches!(framed.next().await.unwrap().unwrap(), Frame::UnSubscribe(_)));
         assert!(framed.next().await.is_none());
     }
+
+    // --- Chunked input ---
+
+    #[test]
+    fn server_codec_assembles_frame_fed_byte_at_a_time() {
+        let unsubscribe = pb::UnSubscribe { subscription_id: 9, max_msgs: None };
+        let mut encoded = BytesMut::new();
+        encode_frame(&unsubscribe, &mut encoded).unwrap();
+        let mut codec = ServerCodec::default();
+        let mut incoming_bytes = BytesMut::new();
+
+        let (last_byte, leading_bytes) = encoded.split_last().unwrap();
+        for byte in leading_bytes {
+            incoming_bytes.put_u8(*byte);
+            assert_eq!(codec.decode(&mut incoming_bytes).unwrap(), None);
+        }
+        incoming_bytes.put_u8(*last_byte);
+
+        assert_eq!(
+            codec.decode(&mut incoming_bytes).unwrap(),
+            Some(Frame::UnSubscribe(unsubscribe))
+        );
+    }
+
+    // --- Read budget ---
+
+    fn partial_publish_frame(declared_payload_length: u32, buffered_payload: usize) -> BytesMut {
+        let mut incoming_bytes = BytesMut::new();
+        incoming_bytes.put_u8(Command::Publish as u8);
+        incoming_bytes.put_u32(declared_payload_length);
+        incoming_bytes.put_bytes(0, buffered_payload);
+        incoming_bytes
+    }
+
+    #[test]
+    fn budgeted_decoders_reject_partial_frames_over_shared_budget() {
+        const BUDGET: usize = 64;
+        let read_budget = ReadBudget::new(BUDGET);
+        let mut first = BudgetedDecoder::new(ServerCodec::default(), read_budget.clone());
+        let mut second = BudgetedDecoder::new(ServerCodec::default(), read_budget);
+
+        let mut first_bytes = partial_publish_frame(1024, 40);
+        assert!(first.decode(&mut first_bytes).unwrap().is_none());
+
+        let mut second_bytes = partial_publish_frame(1024, 40);
+        let result = second.decode(&mut second_bytes);
+        assert!(matches!(
+            result,
+            Err(ServerCodecError::Codec(CodecError::BufferExhausted { budget: BUDGET, .. }))
+        ));
+    }
+
+    #[test]
+    fn budgeted_decoder_releases_charge_on_drop() {
+        const BUDGET: usize = 64;
+        let read_budget = ReadBudget::new(BUDGET);
+        {
+            let mut first = BudgetedDecoder::new(ServerCodec::default(), read_budget.clone());
+            let mut first_bytes = partial_publish_frame(1024, 40);
+            first.decode(&mut first_bytes).unwrap();
+        }
+
+        let mut second = BudgetedDecoder::new(ServerCodec::default(), read_budget);
+        let mut second_bytes = partial_publish_frame(1024, 40);
+        assert!(second.decode(&mut second_bytes).unwrap().is_none());
+    }
 }
@@ -0,0 +1,192 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use futures_util::SinkExt;
+use thiserror::Error;
+use tokio::{
+    io::AsyncWrite,
+    sync::{Mutex, oneshot},
+    time::timeout,
+};
+use tokio_util::codec::FramedWrite;
+
+use crate::{
+    error::{ClientCodecError, ErrorCode},
+    parser::{ClientCodec, ClientFrame, pb},
+};
+
+#[allow(dead_code)]
+#[derive(Debug, Error)]
+pub enum PublishError {
+    #[error(transparent)]
+    Codec(#[from] ClientCodecError),
+    #[error("publish rejected by server: {code:?}")]
+    Rejected { code: ErrorCode, reason: Option<String> },
+    #[error("no acknowledgement within {timeout:?}")]
+    AckTimeout { timeout: Duration },
+    #[error("connection closed before the publish was acknowledged")]
+    ConnectionClosed,
+}
+
+/// Client-side publisher.
+/// On a verbose connection each publish waits for the server's Ack or Error, correlated by
+/// `publish_id`; otherwise it completes as soon as the frame has been written.
+/// Inbound frames must be passed to `on_frame` by the caller's read loop.
+#[allow(dead_code)]
+pub struct Publisher<W: AsyncWrite + Unpin> {
+    framed_write: Mutex<FramedWrite<W, ClientCodec>>,
+    verbose: bool,
+    ack_timeout: Duration,
+    next_publish_id: AtomicU64,
+    pending: DashMap<u64, oneshot::Sender<Result<(), PublishError>>>,
+}
+
+#[allow(dead_code)]
+impl<W: AsyncWrite + Unpin> Publisher<W> {
+    pub fn new(writer: W, verbose: bool, ack_timeout: Duration) -> Self {
+        Self {
+            framed_write: Mutex::new(FramedWrite::new(writer, ClientCodec::default())),
+            verbose,
+            ack_timeout,
+            // 0 is reserved for errors that are not tied to a publish.
+            next_publish_id: AtomicU64::new(1),
+            pending: DashMap::new(),
+        }
+    }
+
+    /// Sends `publish`, overwriting its `publish_id` with a fresh correlation id.
+    pub async fn publish(&self, mut publish: pb::Publish) -> Result<(), PublishError> {
+        if !self.verbose {
+            return Ok(self.framed_write.lock().await.send(publish).await?);
+        }
+
+        let publish_id = self.next_publish_id.fetch_add(1, Ordering::Relaxed);
+        publish.publish_id = publish_id;
+        let (sender, receiver) = oneshot::channel();
+        self.pending.insert(publish_id, sender);
+
+        if let Err(error) = self.framed_write.lock().await.send(publish).await {
+            self.pending.remove(&publish_id);
+            return Err(error.into());
+        }
+
+        match timeout(self.ack_timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(PublishError::ConnectionClosed),
+            Err(_) => {
+                self.pending.remove(&publish_id);
+                Err(PublishError::AckTimeout { timeout: self.ack_timeout })
+            }
+        }
+    }
+
+    /// Resolves the pending publish answered by `frame`, or all of them on Disconnect.
+    /// Other frames are ignored.
+    pub fn on_frame(&self, frame: &ClientFrame) {
+        let (publish_id, result) = match frame {
+            ClientFrame::Ack(ack) => (ack.publish_id, Ok(())),
+            ClientFrame::Error(error) => {
+                // Codes this client does not know are treated as a protocol error.
+                let code = error.error_code().unwrap_or(ErrorCode::ProtocolViolation);
+                (
+                    error.publish_id,
+                    Err(PublishError::Rejected { code, reason: error.reason.clone() }),
+                )
+            }
+            // Dropping the senders fails every pending publish with ConnectionClosed.
+            ClientFrame::Disconnect(_) => return self.pending.clear(),
+            ClientFrame::Info(_)
+            | ClientFrame::Message(_)
+            | ClientFrame::Pong(_)
+            | ClientFrame::SubAck(_)
+            | ClientFrame::Extension(_) => return,
+        };
+        if let Some((_, sender)) = self.pending.remove(&publish_id) {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use tokio::io::{DuplexStream, ReadHalf};
+    use tokio_stream::StreamExt;
+    use tokio_util::codec::FramedRead;
+
+    use super::*;
+    use crate::parser::{Frame, ServerCodec, ServerOutbound};
+
+    const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+    const REJECTED_TOPIC: &[u8] = b"rejected";
+
+    /// Loopback server that acks every publish except those sent to `REJECTED_TOPIC`.
+    async fn run_loopback_server(stream: DuplexStream) {
+        let (reader, writer) = tokio::io::split(stream);
+        let mut framed_read = FramedRead::new(reader, ServerCodec::default());
+        let mut framed_write = FramedWrite::new(writer, ServerCodec::default());
+        while let Some(Ok(Frame::Publish(publish))) = framed_read.next().await {
+            if publish.topic == REJECTED_TOPIC {
+                let error = ServerOutbound::error(
+                    publish.publish_id,
+                    ErrorCode::ProtocolViolation,
+                    Some("rejected".to_string()),
+                );
+                framed_write.send(error).await.unwrap();
+            } else {
+                framed_write.send(pb::Ack { publish_id: publish.publish_id }).await.unwrap();
+            }
+        }
+    }
+
+    fn spawn_frame_reader(
+        publisher: Arc<Publisher<tokio::io::WriteHalf<DuplexStream>>>,
+        reader: ReadHalf<DuplexStream>,
+    ) {
+        tokio::spawn(async move {
+            let mut framed_read = FramedRead::new(reader, ClientCodec::default());
+            while let Some(Ok(frame)) = framed_read.next().await {
+                publisher.on_frame(&frame);
+            }
+        });
+    }
+
+    fn verbose_publisher() -> Arc<Publisher<tokio::io::WriteHalf<DuplexStream>>> {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        tokio::spawn(run_loopback_server(server_io));
+        let (reader, writer) = tokio::io::split(client_io);
+        let publisher = Arc::new(Publisher::new(writer, true, ACK_TIMEOUT));
+        spawn_frame_reader(Arc::clone(&publisher), reader);
+        publisher
+    }
+
+    #[tokio::test]
+    async fn verbose_publish_resolves_on_ack() {
+        let publisher = verbose_publisher();
+
+        let result = publisher
+            .publish(pb::Publish { topic: Bytes::from_static(b"a/b"), ..Default::default() })
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn verbose_publish_fails_on_server_error() {
+        let publisher = verbose_publisher();
+
+        let result = publisher
+            .publish(pb::Publish {
+                topic: Bytes::from_static(REJECTED_TOPIC),
+                ..Default::default()
+            })
+            .await;
+
+        assert!(matches!(result, Err(PublishError::Rejected { .. })));
+    }
+}
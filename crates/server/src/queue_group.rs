@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Members of one queue group on a matched subscription.
+/// Each message goes to exactly one member, chosen round-robin in insertion order; plain
+/// subscribers outside any group are not tracked here and each receive their own copy.
+#[allow(dead_code)]
+pub struct QueueGroup<T> {
+    members: Vec<T>,
+    cursor: AtomicUsize,
+}
+
+#[allow(dead_code)]
+impl<T> QueueGroup<T> {
+    pub fn new() -> Self {
+        Self { members: Vec::new(), cursor: AtomicUsize::new(0) }
+    }
+
+    pub fn add(&mut self, member: T) {
+        self.members.push(member);
+    }
+
+    /// Removes the first member equal to `member`. Returns whether one was found.
+    pub fn remove(&mut self, member: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let Some(position) = self.members.iter().position(|candidate| candidate == member) else {
+            return false;
+        };
+        self.members.remove(position);
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Picks the member that receives the next message, or `None` for an empty group.
+    pub fn select(&self) -> Option<&T> {
+        if self.members.is_empty() {
+            return None;
+        }
+        let turn = self.cursor.fetch_add(1, Ordering::Relaxed);
+        self.members.get(turn % self.members.len())
+    }
+}
+
+impl<T> Default for QueueGroup<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn select_distributes_round_robin() {
+        const MESSAGE_COUNT: usize = 10;
+        let mut group = QueueGroup::new();
+        for member in ["a", "b", "c"] {
+            group.add(member);
+        }
+
+        let mut received: HashMap<&str, usize> = HashMap::new();
+        for _ in 0..MESSAGE_COUNT {
+            *received.entry(*group.select().unwrap()).or_default() += 1;
+        }
+
+        assert_eq!(received, HashMap::from([("a", 4), ("b", 3), ("c", 3)]));
+    }
+
+    #[test]
+    fn select_on_empty_group_is_none() {
+        let group: QueueGroup<u32> = QueueGroup::new();
+
+        assert!(group.select().is_none());
+    }
+
+    #[test]
+    fn removed_member_is_no_longer_selected() {
+        let mut group = QueueGroup::new();
+        group.add(1);
+        group.add(2);
+
+        assert!(group.remove(&1));
+
+        assert_eq!(group.select(), Some(&2));
+        assert_eq!(group.select(), Some(&2));
+    }
+}
@@ -1,13 +1,21 @@
 use std::{error::Error, net::SocketAddr, sync::Arc};
 
-use s2n_quic::{Server, provider::endpoint_limits, stream::BidirectionalStream};
+use s2n_quic::{
+    Server, application, connection::Handle, provider::endpoint_limits, stream::BidirectionalStream,
+};
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
+#[cfg(feature = "trace")]
+use crate::trace::FrameTracer;
 use crate::{
     auth::{Authenticator, NoAuthAuthenticator},
-    client::{Client, ClientError},
+    client::{Client, ClientError, SessionServices},
     config::ServerConfig,
+    dedup::DedupWindow,
+    error::ErrorCode,
+    interceptor::InterceptorChain,
+    parser::ReadBudget,
     transport::Transport,
 };
 
@@ -20,20 +28,69 @@ impl Transport for BidirectionalStream {
     }
 }
 
+/// Closes the connection with a QUIC CONNECTION_CLOSE carrying `code` as the application error.
+/// s2n-quic does not transmit a reason phrase for application closes, so `reason` is only logged.
+pub fn close_with(connection: &Handle, code: ErrorCode, reason: &str) {
+    info!("closing QUIC connection code={:?} reason={}", code, reason);
+    connection.close(application::Error::from(code as u8));
+}
+
 async fn handle_bidirectional_stream(
     stream: BidirectionalStream,
+    connection: Handle,
     config: Arc<ServerConfig>,
-    authenticator: Arc<dyn Authenticator>,
+    services: SessionServices,
+    read_budget: ReadBudget,
 ) -> Result<(), ClientError> {
-    let client = Client::new(stream, authenticator, config);
-    client.run().await
+    let client = Client::with_services(stream, services, config, read_budget);
+    let result = client.run().await;
+    if let Err(error) = &result {
+        close_with(&connection, error.error_code(), &error.to_string());
+    }
+    result
 }
 
 pub async fn start(
     config: Arc<ServerConfig>,
     shutdown: CancellationToken,
+) -> Result<SocketAddr, Box<dyn Error + Send + Sync>> {
+    start_with_authenticator(config, Arc::new(NoAuthAuthenticator), shutdown).await
+}
+
+/// Starts the QUIC server, validating every CONNECT with `authenticator`.
+pub async fn start_with_authenticator(
+    config: Arc<ServerConfig>,
+    authenticator: Arc<dyn Authenticator>,
+    shutdown: CancellationToken,
+) -> Result<SocketAddr, Box<dyn Error + Send + Sync>> {
+    start_with_interceptors(config, authenticator, InterceptorChain::new(), shutdown).await
+}
+
+/// Starts the QUIC server like `start_with_authenticator`, also running every Publish,
+/// delivery and Extension through `interceptors`.
+pub async fn start_with_interceptors(
+    config: Arc<ServerConfig>,
+    authenticator: Arc<dyn Authenticator>,
+    interceptors: InterceptorChain,
+    shutdown: CancellationToken,
 ) -> Result<SocketAddr, Box<dyn Error + Send + Sync>> {
     let addr: SocketAddr = config.quic.socket_addr();
+    let services = SessionServices {
+        interceptors: Arc::new(interceptors),
+        dedup: config
+            .quic
+            .dedup_window_ms
+            .map(|window_ms| Arc::new(DedupWindow::new(window_ms, config.quic.dedup_max_entries))),
+        ..SessionServices::new(authenticator)
+    };
+    #[cfg(feature = "trace")]
+    let services = match &config.trace_file_path {
+        Some(path) => SessionServices {
+            tracer: Some(Arc::new(FrameTracer::create(path, Arc::clone(&services.clock))?)),
+            ..services
+        },
+        None => services,
+    };
 
     let io = s2n_quic::provider::io::Default::builder()
         .with_receive_address(addr)?
@@ -67,8 +124,6 @@ pub async fn start(
     let local_addr = server.local_addr()?;
     info!("Ocypode server listening to {}", local_addr);
 
-    let authenticator: Arc<dyn Authenticator> = Arc::new(NoAuthAuthenticator);
-
     tokio::spawn(async move {
         loop {
             tokio::select! {
@@ -79,13 +134,17 @@ pub async fn start(
                 connection = server.accept() => {
                     if let Some(mut connection) = connection {
                         let config = Arc::clone(&config);
-                        let authenticator = Arc::clone(&authenticator);
+                        let services = services.clone();
                         tokio::spawn(async move {
+                            let handle = connection.handle();
+                            let read_budget = ReadBudget::new(config.quic.connection_read_budget);
                             while let Ok(Some(stream)) = connection.accept_bidirectional_stream().await {
+                                let handle = handle.clone();
                                 let config = Arc::clone(&config);
-                                let auth = Arc::clone(&authenticator);
+                                let services = services.clone();
+                                let read_budget = read_budget.clone();
                                 tokio::spawn(async move {
-                                    if let Err(error) = handle_bidirectional_stream(stream, config, auth).await {
+                                    if let Err(error) = handle_bidirectional_stream(stream, handle, config, services, read_budget).await {
                                         info!("QUIC stream error: {}", error);
                                     }
                                 });
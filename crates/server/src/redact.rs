@@ -0,0 +1,259 @@
+//! Log-safe formatting for protocol messages.
+//!
+//! `Debug` for the messages that carry payloads or credentials is written here instead of
+//! derived (see `skip_debug` in build.rs): topics print as text, payloads and headers print
+//! only their size, and secrets never print at all. `Display` on frames gives a one-line
+//! summary for trace logs.
+
+use std::fmt;
+
+use crate::parser::{ClientFrame, Frame, pb};
+
+/// Bytes that are usually UTF-8, such as topics, printed as a quoted string.
+struct Text<'a>(&'a [u8]);
+
+impl fmt::Debug for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&String::from_utf8_lossy(self.0), f)
+    }
+}
+
+impl fmt::Display for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(self.0))
+    }
+}
+
+/// Opaque application bytes, printed as their length only.
+struct Opaque(usize);
+
+impl fmt::Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} bytes>", self.0)
+    }
+}
+
+/// A secret, printed without revealing even its length.
+struct Secret;
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl fmt::Debug for pb::Publish {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Publish")
+            .field("topic", &Text(&self.topic))
+            .field("payload", &Opaque(self.payload.len()))
+            .field("header", &Opaque(self.header.len()))
+            .field("publish_id", &self.publish_id)
+            .field("reply_to", &self.reply_to.as_deref().map(Text))
+            .field("retain", &self.retain)
+            .field("topic_alias", &self.topic_alias)
+            .field("fragment", &self.fragment)
+            .field("qos", &self.qos)
+            .field("priority", &self.priority)
+            .finish()
+    }
+}
+
+impl fmt::Debug for pb::Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Message")
+            .field("topic", &Text(&self.topic))
+            .field("subscription_id", &self.subscription_id)
+            .field("payload", &Opaque(self.payload.len()))
+            .field("header", &Opaque(self.header.len()))
+            .field("reply_to", &self.reply_to.as_deref().map(Text))
+            .field("sequence", &self.sequence)
+            .field("retained", &self.retained)
+            .field("fragment", &self.fragment)
+            .field("priority", &self.priority)
+            .field("received_at_ms", &self.received_at_ms)
+            .field("delivery_id", &self.delivery_id)
+            .finish()
+    }
+}
+
+impl fmt::Debug for pb::Will {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Will")
+            .field("topic", &Text(&self.topic))
+            .field("payload", &Opaque(self.payload.len()))
+            .field("header", &Opaque(self.header.len()))
+            .field("retain", &self.retain)
+            .finish()
+    }
+}
+
+impl fmt::Debug for pb::PasswordAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PasswordAuth")
+            .field("username", &self.username)
+            .field("password", &Secret)
+            .finish()
+    }
+}
+
+impl fmt::Debug for pb::BearerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BearerToken").field("token", &Secret).finish()
+    }
+}
+
+impl fmt::Debug for pb::ChallengeAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChallengeAuth")
+            .field("public_key", &Opaque(self.public_key.len()))
+            .field("signature", &Secret)
+            .finish()
+    }
+}
+
+impl fmt::Debug for pb::Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extension")
+            .field("command", &self.command)
+            .field("payload", &Opaque(self.payload.len()))
+            .finish()
+    }
+}
+
+/// One line per inbound frame, e.g. `PUBLISH topic=sensors/a payload=12B header=0B id=3`.
+/// Credentials, payloads and headers are never printed.
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frame::Connect(connect) => write!(
+                f,
+                "CONNECT version={} verbose={} auth={}",
+                connect.version,
+                connect.verbose,
+                connect.auth_method().as_str_name()
+            ),
+            Frame::Publish(publish) => write!(
+                f,
+                "PUBLISH topic={} payload={}B header={}B id={}",
+                Text(&publish.topic),
+                publish.payload.len(),
+                publish.header.len(),
+                publish.publish_id
+            ),
+            Frame::Subscribe(subscribe) => write!(
+                f,
+                "SUBSCRIBE topic={} sid={}",
+                Text(&subscribe.topic),
+                subscribe.subscription_id
+            ),
+            Frame::UnSubscribe(unsubscribe) => {
+                write!(f, "UNSUBSCRIBE sid={}", unsubscribe.subscription_id)
+            }
+            Frame::Flow(flow) => {
+                write!(f, "FLOW sid={} credits={}", flow.subscription_id, flow.credits)
+            }
+            Frame::MessageAck(ack) => write!(f, "MSGACK id={}", ack.delivery_id),
+            Frame::Ping(_) => f.write_str("PING"),
+            Frame::Disconnect(disconnect) => {
+                write!(f, "DISCONNECT reason={}", disconnect.reason().as_str_name())
+            }
+            Frame::Extension(extension) => {
+                write!(f, "EXT command={} payload={}B", extension.command, extension.payload.len())
+            }
+        }
+    }
+}
+
+/// One line per frame received by a client, in the same style as `Frame`.
+impl fmt::Display for ClientFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientFrame::Info(info) => write!(
+                f,
+                "INFO version={} server_id={} client_id={}",
+                info.version, info.server_id, info.client_id
+            ),
+            ClientFrame::Message(message) => write!(
+                f,
+                "MESSAGE topic={} sid={} payload={}B header={}B",
+                Text(&message.topic),
+                message.subscription_id,
+                message.payload.len(),
+                message.header.len()
+            ),
+            ClientFrame::Ack(ack) => write!(f, "ACK id={}", ack.publish_id),
+            ClientFrame::Error(error) => {
+                write!(f, "ERROR id={} code={} fatal={}", error.publish_id, error.code, error.fatal)
+            }
+            ClientFrame::Pong(_) => f.write_str("PONG"),
+            ClientFrame::SubAck(sub_ack) => {
+                write!(f, "SUBACK sid={} granted={}", sub_ack.subscription_id, sub_ack.granted)
+            }
+            ClientFrame::Disconnect(disconnect) => {
+                write!(f, "DISCONNECT reason={}", disconnect.reason().as_str_name())
+            }
+            ClientFrame::Extension(extension) => {
+                write!(f, "EXT command={} payload={}B", extension.command, extension.payload.len())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn publish_debug_shows_topic_but_not_payload() {
+        let publish = pb::Publish {
+            topic: Bytes::from_static(b"sensors/a"),
+            payload: Bytes::from_static(b"secret reading"),
+            ..Default::default()
+        };
+
+        let rendered = format!("{publish:?}");
+
+        assert!(rendered.contains("\"sensors/a\""));
+        assert!(!rendered.contains("secret reading"));
+    }
+
+    #[test]
+    fn password_debug_is_redacted() {
+        let auth =
+            pb::PasswordAuth { username: "bob".to_string(), password: "hunter2".to_string() };
+
+        assert!(!format!("{auth:?}").contains("hunter2"));
+    }
+
+    #[test]
+    fn bearer_token_debug_is_redacted() {
+        let token = pb::BearerToken { token: b"api-key-123".to_vec() };
+
+        assert!(!format!("{token:?}").contains("api-key-123"));
+    }
+
+    #[test]
+    fn challenge_auth_debug_is_redacted() {
+        let auth = pb::ChallengeAuth { public_key: vec![7; 32], signature: vec![9; 64] };
+
+        assert_eq!(
+            format!("{auth:?}"),
+            "ChallengeAuth { public_key: <32 bytes>, signature: <redacted> }"
+        );
+    }
+
+    #[test]
+    fn publish_frame_displays_on_one_line() {
+        let frame = Frame::Publish(pb::Publish {
+            topic: Bytes::from_static(b"sensors/a"),
+            payload: Bytes::from(vec![0; 12]),
+            publish_id: 3,
+            ..Default::default()
+        });
+
+        assert_eq!(frame.to_string(), "PUBLISH topic=sensors/a payload=12B header=0B id=3");
+    }
+}
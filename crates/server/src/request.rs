@@ -0,0 +1,225 @@
+#![allow(dead_code)]
+
+use std::fmt::Write;
+
+use bytes::{Bytes, BytesMut};
+
+pub use crate::error::RequestError;
+use crate::{
+    auth::fill_random,
+    headers::{Headers, HeadersError, RESERVED_PREFIX},
+    parser::pb,
+    topic::{Topic, TopicError},
+};
+
+/// First layer of every generated reply inbox.
+pub const INBOX_PREFIX: &str = "_INBOX";
+/// Hex digits in the unique layer after `INBOX_PREFIX`, which doubles as the correlation id.
+pub const INBOX_TOKEN_LENGTH: usize = 32;
+/// Header name, under `RESERVED_PREFIX`, carrying a request's correlation id. A `Reply`
+/// copies it back so a requester sharing one inbox can match responses to requests.
+pub const CORRELATION_ID_HEADER: &str = "Correlation-Id";
+
+/// Random bytes behind an inbox token; each becomes two hex digits.
+const INBOX_TOKEN_BYTES: usize = INBOX_TOKEN_LENGTH / 2;
+
+/// A Publish that expects a response on its own reply inbox.
+/// Subscribe to `inbox()` before sending `into_publish()` so the response cannot be missed.
+#[derive(Debug, Clone)]
+pub struct Request {
+    inbox: Topic,
+    correlation_id: Bytes,
+    publish: pb::Publish,
+}
+
+impl Request {
+    /// Fails if `headers` plus the correlation id no longer fit in a header block, or if the
+    /// operating system cannot supply random bytes for the inbox.
+    pub fn new(
+        topic: &Topic,
+        payload: impl Into<Bytes>,
+        mut headers: Headers,
+    ) -> Result<Self, RequestError> {
+        let token = new_inbox_token()?;
+        let inbox = Topic::try_from(Bytes::from(format!("{INBOX_PREFIX}/{token}")))?;
+        let correlation_id = Bytes::from(token);
+        headers.insert_reserved(CORRELATION_ID_HEADER, correlation_id.clone())?;
+        let publish = pb::Publish {
+            topic: topic.to_bytes(),
+            payload: payload.into(),
+            header: encode_headers(&headers)?,
+            reply_to: Some(inbox.to_bytes()),
+            ..Default::default()
+        };
+        Ok(Self { inbox, correlation_id, publish })
+    }
+
+    pub fn inbox(&self) -> &Topic {
+        &self.inbox
+    }
+
+    pub fn correlation_id(&self) -> &Bytes {
+        &self.correlation_id
+    }
+
+    pub fn into_publish(self) -> pb::Publish {
+        self.publish
+    }
+}
+
+/// A Publish answering a received request: addressed to its `reply_to` and carrying its
+/// correlation id, if it had one.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    publish: pb::Publish,
+}
+
+impl Reply {
+    pub fn new(
+        request: &pb::Message,
+        payload: impl Into<Bytes>,
+        mut headers: Headers,
+    ) -> Result<Self, RequestError> {
+        let topic = reply_to(request)?.ok_or(RequestError::NoReplyTo)?;
+        if let Some(correlation_id) = correlation_id(&request.header)? {
+            headers.insert_reserved(CORRELATION_ID_HEADER, correlation_id)?;
+        }
+        let publish = pb::Publish {
+            topic: topic.to_bytes(),
+            payload: payload.into(),
+            header: encode_headers(&headers)?,
+            ..Default::default()
+        };
+        Ok(Self { publish })
+    }
+
+    pub fn into_publish(self) -> pb::Publish {
+        self.publish
+    }
+}
+
+/// Returns the topic a responder should publish back to, if `message` carries one.
+pub fn reply_to(message: &pb::Message) -> Result<Option<Topic>, TopicError> {
+    message.reply_to.as_deref().map(|reply_to| Topic::new(BytesMut::from(reply_to))).transpose()
+}
+
+/// Reads the correlation id from an encoded header block, as set by `Request` or `Reply`.
+pub fn correlation_id(header: &Bytes) -> Result<Option<Bytes>, HeadersError> {
+    if header.is_empty() {
+        return Ok(None);
+    }
+    let headers = Headers::decode_from(&mut header.clone())?;
+    Ok(headers.get(&format!("{RESERVED_PREFIX}{CORRELATION_ID_HEADER}")).cloned())
+}
+
+/// True when `topic` lies under `INBOX_PREFIX`. Inboxes have exactly one subscriber, so
+/// routing can resolve them with an exact lookup and skip wildcard matching.
+pub fn is_inbox(topic: &Topic) -> bool {
+    topic.segments().next() == Some(INBOX_PREFIX.as_bytes())
+}
+
+fn encode_headers(headers: &Headers) -> Result<Bytes, HeadersError> {
+    if headers.is_empty() {
+        return Ok(Bytes::new());
+    }
+    let mut header = BytesMut::with_capacity(headers.encoded_len());
+    headers.encode_to(&mut header)?;
+    Ok(header.freeze())
+}
+
+/// `INBOX_TOKEN_LENGTH` hex digits from a cryptographically secure generator, so other
+/// clients cannot guess an inbox and answer in the responder's place.
+fn new_inbox_token() -> Result<String, RequestError> {
+    let mut token_bytes = [0; INBOX_TOKEN_BYTES];
+    fill_random(&mut token_bytes).map_err(|error| RequestError::Random(error.kind()))?;
+    Ok(token_bytes.iter().fold(String::with_capacity(INBOX_TOKEN_LENGTH), |mut token, byte| {
+        let _ = write!(token, "{byte:02x}");
+        token
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topic(s: &str) -> Topic {
+        Topic::new(BytesMut::from(s)).unwrap()
+    }
+
+    fn request() -> Request {
+        Request::new(&topic("service/echo"), b"ping".to_vec(), Headers::new()).unwrap()
+    }
+
+    /// The Message a responder receives for `request`.
+    fn delivered(request: Request) -> pb::Message {
+        let publish = request.into_publish();
+        pb::Message {
+            topic: publish.topic,
+            payload: publish.payload,
+            header: publish.header,
+            reply_to: publish.reply_to,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn requests_get_distinct_valid_inboxes() {
+        let first = request();
+        let second = request();
+
+        assert_ne!(first.inbox(), second.inbox());
+        for request in [first, second] {
+            let inbox = request.inbox().clone();
+            let reply_to = request.into_publish().reply_to.unwrap();
+            assert_eq!(Topic::new(BytesMut::from(&reply_to[..])), Ok(inbox));
+        }
+    }
+
+    #[test]
+    fn request_carries_its_correlation_id_header() {
+        let request = request();
+        let expected = request.correlation_id().clone();
+
+        let header = request.into_publish().header;
+
+        assert_eq!(correlation_id(&header), Ok(Some(expected)));
+    }
+
+    #[test]
+    fn reply_goes_to_reply_to_with_the_request_correlation_id() {
+        let request = request();
+        let inbox = request.inbox().clone();
+        let expected = request.correlation_id().clone();
+
+        let reply = Reply::new(&delivered(request), b"pong".to_vec(), Headers::new())
+            .unwrap()
+            .into_publish();
+
+        assert_eq!(reply.topic, inbox.to_bytes());
+        assert_eq!(correlation_id(&reply.header), Ok(Some(expected)));
+    }
+
+    #[test]
+    fn reply_to_message_without_reply_to_is_rejected() {
+        let message = pb::Message::default();
+
+        let result = Reply::new(&message, Bytes::new(), Headers::new());
+
+        assert!(matches!(result, Err(RequestError::NoReplyTo)));
+    }
+
+    #[test]
+    fn reply_to_extracts_topic_from_message() {
+        let message =
+            pb::Message { reply_to: Some(Bytes::from_static(b"_INBOX/abc")), ..Default::default() };
+
+        assert_eq!(reply_to(&message), Ok(Some(topic("_INBOX/abc"))));
+    }
+
+    #[test]
+    fn is_inbox_matches_only_the_inbox_prefix_layer() {
+        assert!(is_inbox(request().inbox()));
+        assert!(!is_inbox(&topic("_INBOXES/abc")));
+        assert!(!is_inbox(&topic("service/_INBOX")));
+    }
+}
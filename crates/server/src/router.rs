@@ -111,9 +111,51 @@ impl Router {
     }
 
     pub(crate) fn search(&self, topic: &Topic) -> SubscriptionResponse {
-        let segments: Vec<&[u8]> = topic.segments().collect();
         let mut subscription_list = Vec::new();
         let mut queue_group_list = Vec::new();
+        self.visit_matching_nodes(topic, |node| {
+            collect_node(node, &mut subscription_list, &mut queue_group_list)
+        });
+        SubscriptionResponse { subscription_list, queue_group_list }
+    }
+
+    /// Like `search`, but leaves out every subscription held by `publisher`. Used for
+    /// Publishes from connections that set `Connect.no_echo`; a queue group left with no
+    /// other members is dropped.
+    pub(crate) fn search_without_echo(
+        &self,
+        topic: &Topic,
+        publisher: ClientId,
+    ) -> SubscriptionResponse {
+        let mut response = self.search(topic);
+        response.subscription_list.retain(|(client_id, _)| *client_id != publisher);
+        for group in &mut response.queue_group_list {
+            group.retain(|(client_id, _)| *client_id != publisher);
+        }
+        response.queue_group_list.retain(|group| !group.is_empty());
+        response
+    }
+
+    /// Maps every subscription to the topics in `active` it currently matches.
+    /// Intended for monitoring, where the set of live publish topics is known up front.
+    pub(crate) fn matched_topics<'a>(
+        &self,
+        active: &'a [Topic],
+    ) -> HashMap<SubscriptionKey, Vec<&'a Topic>> {
+        let mut matched: HashMap<SubscriptionKey, Vec<&'a Topic>> = HashMap::new();
+        for topic in active {
+            self.visit_matching_nodes(topic, |node| {
+                let queue_group_keys = node.queue_group_map.values().flat_map(|group| group.keys());
+                for key in node.subscription_map.keys().chain(queue_group_keys) {
+                    matched.entry(*key).or_default().push(topic);
+                }
+            });
+        }
+        matched
+    }
+
+    fn visit_matching_nodes<'a>(&'a self, topic: &Topic, mut visit: impl FnMut(&'a Node)) {
+        let segments: Vec<&[u8]> = topic.segments().collect();
 
         // Stack of (node, remaining_segments).
         let mut stack: Vec<(&Node, &[&[u8]])> = vec![(&self.root, &segments)];
@@ -128,11 +170,11 @@ impl Router {
                     .as_ref()
                     .and_then(|c| c.iter().find(|n| n.level.as_ref() == WILDCARD_MULTI))
             {
-                collect_node(multi_child, &mut subscription_list, &mut queue_group_list);
+                visit(multi_child);
             }
 
             let [segment, rest @ ..] = remaining else {
-                collect_node(node, &mut subscription_list, &mut queue_group_list);
+                visit(node);
                 continue;
             };
 
@@ -144,8 +186,6 @@ impl Router {
                 }
             }
         }
-
-        SubscriptionResponse { subscription_list, queue_group_list }
     }
 
     pub(crate) fn delete(&mut self, subscription_key: SubscriptionKey) {
@@ -400,6 +440,24 @@ mod tests {
         assert_eq!(result.subscription_list.len(), 3);
     }
 
+    #[test]
+    fn search_returns_overlapping_filters_when_topic_ends_early() {
+        let mut router = Router::new();
+        router.insert(dummy_tx(), ClientId::new(), 1, make_filter("a"));
+        router.insert(dummy_tx(), ClientId::new(), 2, make_filter("+"));
+        router.insert(dummy_tx(), ClientId::new(), 3, make_filter("a/#"));
+        router.insert(dummy_tx(), ClientId::new(), 4, make_filter("a/b"));
+        router.insert(dummy_tx(), ClientId::new(), 5, make_filter("a/+"));
+        let result = router.search(&make_topic("a"));
+        let mut subscription_ids: Vec<u32> = result
+            .subscription_list
+            .iter()
+            .map(|(_, subscription)| subscription.subscription_id)
+            .collect();
+        subscription_ids.sort();
+        assert_eq!(subscription_ids, vec![1, 2, 3]);
+    }
+
     #[test]
     fn search_non_matching_sibling_not_returned() {
         let mut router = Router::new();
@@ -411,6 +469,43 @@ mod tests {
         assert_eq!(result.subscription_list[0].0, client_id);
     }
 
+    #[test]
+    fn search_without_echo_skips_publisher_subscriptions() {
+        let mut router = Router::new();
+        let publisher = ClientId::new();
+        let other = ClientId::new();
+        router.insert(dummy_tx(), publisher, 1, make_filter("a/#"));
+        router.insert(dummy_tx(), other, 2, make_filter("a/+"));
+        let result = router.search_without_echo(&make_topic("a/b"), publisher);
+        assert_eq!(result.subscription_list.len(), 1);
+        assert_eq!(result.subscription_list[0].0, other);
+    }
+
+    #[test]
+    fn matched_topics_maps_each_subscription_to_active_topics() {
+        let mut router = Router::new();
+        let client_id = ClientId::new();
+        router.insert(dummy_tx(), client_id, 1, make_filter("a/b"));
+        router.insert(dummy_tx(), client_id, 2, make_filter("a/+"));
+        router.insert(dummy_tx(), client_id, 3, make_filter("#"));
+        let active = [make_topic("a/b"), make_topic("a/c"), make_topic("x")];
+
+        let matched = router.matched_topics(&active);
+
+        let topics_for = |subscription_id| {
+            let mut topics: Vec<String> = matched
+                [&SubscriptionKey::new(client_id, subscription_id)]
+                .iter()
+                .map(|topic| topic.to_string())
+                .collect();
+            topics.sort();
+            topics
+        };
+        assert_eq!(topics_for(1), vec!["a/b"]);
+        assert_eq!(topics_for(2), vec!["a/b", "a/c"]);
+        assert_eq!(topics_for(3), vec!["a/b", "a/c", "x"]);
+    }
+
     #[test]
     fn delete_removes_subscription_from_leaf() {
         let mut router = Router::new();
@@ -0,0 +1,199 @@
+#[cfg(test)]
+use std::cell::Cell;
+use std::collections::HashMap;
+
+use crate::topic::{Topic, TopicFilter};
+
+#[allow(dead_code)]
+const BLOOM_COUNTERS: usize = 4096;
+#[allow(dead_code)]
+const BLOOM_HASHES: usize = 3;
+// 64-bit FNV-1a parameters.
+#[allow(dead_code)]
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+#[allow(dead_code)]
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Exact-match subscriptions keyed by topic, fronted by a counting Bloom filter.
+/// A publish to a topic nobody subscribed to is answered from the filter alone, without a
+/// map probe. False positives fall through to the map, which is authoritative.
+/// Wildcard filters are not accepted; they belong in the router trie.
+#[allow(dead_code)]
+pub struct SubscriptionIndex<T> {
+    exact: HashMap<Topic, Vec<T>>,
+    // Counters rather than bits so removing a topic does not clear bits shared with others.
+    bloom: Box<[u16; BLOOM_COUNTERS]>,
+    /// Map lookups made by `lookup`, so tests can see the filter short-circuit.
+    #[cfg(test)]
+    map_probes: Cell<usize>,
+}
+
+#[allow(dead_code)]
+impl<T> SubscriptionIndex<T> {
+    pub fn new() -> Self {
+        Self {
+            exact: HashMap::new(),
+            bloom: Box::new([0; BLOOM_COUNTERS]),
+            #[cfg(test)]
+            map_probes: Cell::new(0),
+        }
+    }
+
+    /// Adds `value` under `filter`. A wildcard filter is handed back so the caller can route
+    /// it to the trie instead.
+    pub fn insert(&mut self, filter: TopicFilter, value: T) -> Result<(), TopicFilter> {
+        let Some(topic) = filter.to_topic() else { return Err(filter) };
+        let indexes = bloom_indexes(topic.as_bytes());
+        let values = self.exact.entry(topic).or_default();
+        if values.is_empty() {
+            for index in indexes {
+                self.bloom[index] = self.bloom[index].saturating_add(1);
+            }
+        }
+        values.push(value);
+        Ok(())
+    }
+
+    /// Removes the first value under `filter` equal to `value`. Returns whether one was found.
+    pub fn remove(&mut self, filter: &TopicFilter, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let Some(topic) = filter.to_topic() else { return false };
+        let Some(values) = self.exact.get_mut(&topic) else { return false };
+        let Some(position) = values.iter().position(|candidate| candidate == value) else {
+            return false;
+        };
+        values.remove(position);
+        if values.is_empty() {
+            self.exact.remove(&topic);
+            for index in bloom_indexes(topic.as_bytes()) {
+                // A saturated counter no longer knows its true count, so it is left as is.
+                if self.bloom[index] != u16::MAX {
+                    self.bloom[index] -= 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the values subscribed to exactly `topic`.
+    pub fn lookup(&self, topic: &Topic) -> &[T] {
+        if !self.might_contain(topic) {
+            return &[];
+        }
+        #[cfg(test)]
+        self.map_probes.set(self.map_probes.get() + 1);
+        self.exact.get(topic).map_or(&[], Vec::as_slice)
+    }
+
+    /// False means `topic` definitely has no exact subscribers; true may be a false positive.
+    pub fn might_contain(&self, topic: &Topic) -> bool {
+        bloom_indexes(topic.as_bytes()).into_iter().all(|index| self.bloom[index] > 0)
+    }
+}
+
+impl<T> Default for SubscriptionIndex<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Derives the counter positions by double hashing one 64-bit hash. FNV-1a is used rather
+/// than the map's SipHash so the filter check stays cheaper than the probe it replaces; the
+/// filter needs no protection against chosen collisions, which only cost a map probe.
+#[allow(dead_code)]
+fn bloom_indexes(topic: &[u8]) -> [usize; BLOOM_HASHES] {
+    let hash = topic
+        .iter()
+        .fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME));
+    let (first, second) = (hash as u32 as usize, (hash >> 32) as usize);
+    std::array::from_fn(|i| first.wrapping_add(i.wrapping_mul(second)) % BLOOM_COUNTERS)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+
+    fn filter(s: &str) -> TopicFilter {
+        TopicFilter::new(BytesMut::from(s)).unwrap()
+    }
+
+    fn topic(s: &str) -> Topic {
+        Topic::new(BytesMut::from(s)).unwrap()
+    }
+
+    #[test]
+    fn lookup_returns_exact_subscribers() {
+        let mut index = SubscriptionIndex::new();
+        index.insert(filter("sensors/a"), 1).unwrap();
+        index.insert(filter("sensors/a"), 2).unwrap();
+        index.insert(filter("sensors/b"), 3).unwrap();
+
+        assert_eq!(index.lookup(&topic("sensors/a")), &[1, 2]);
+    }
+
+    #[test]
+    fn insert_hands_back_wildcard_filters() {
+        let mut index = SubscriptionIndex::new();
+
+        let result = index.insert(filter("sensors/+"), 1);
+
+        assert_eq!(result, Err(filter("sensors/+")));
+    }
+
+    #[test]
+    fn no_match_lookup_never_touches_the_map() {
+        const LOOKUPS: usize = 1_000;
+        let mut index = SubscriptionIndex::new();
+        index.insert(filter("sensors/a"), 1).unwrap();
+
+        for _ in 0..LOOKUPS {
+            index.lookup(&topic("sensors/b"));
+        }
+
+        assert_eq!(index.map_probes.get(), 0);
+    }
+
+    #[test]
+    fn bloom_false_positive_falls_through_to_the_map() {
+        // Enough topics to set most counters, so some unsubscribed topic passes the filter.
+        const FILLER_TOPICS: usize = BLOOM_COUNTERS / 2;
+        let mut index = SubscriptionIndex::new();
+        for n in 0..FILLER_TOPICS {
+            index.insert(filter(&format!("fill/{n}")), n).unwrap();
+        }
+        let false_positive = (0..)
+            .map(|n| topic(&format!("probe/{n}")))
+            .find(|candidate| index.might_contain(candidate))
+            .unwrap();
+
+        index.lookup(&false_positive);
+
+        assert_eq!(index.map_probes.get(), 1);
+    }
+
+    #[test]
+    fn removing_last_subscriber_clears_the_filter() {
+        let mut index = SubscriptionIndex::new();
+        index.insert(filter("sensors/a"), 1).unwrap();
+
+        index.remove(&filter("sensors/a"), &1);
+
+        assert!(!index.might_contain(&topic("sensors/a")));
+    }
+
+    #[test]
+    fn remove_keeps_other_topics_visible() {
+        let mut index = SubscriptionIndex::new();
+        index.insert(filter("sensors/a"), 1).unwrap();
+        index.insert(filter("sensors/b"), 2).unwrap();
+
+        assert!(index.remove(&filter("sensors/a"), &1));
+
+        assert!(index.lookup(&topic("sensors/a")).is_empty());
+        assert_eq!(index.lookup(&topic("sensors/b")), &[2]);
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 use bytes::{Bytes, BytesMut};
 
@@ -24,14 +24,35 @@ pub const GLOBAL_PREFIX: &[u8] = b"$G";
 
 const SEP_BYTE: u8 = b'/';
 
+const NUL_BYTE: u8 = 0;
+
+/// Size limits applied when validating topics and filters.
+/// The defaults are `MAX_TOPIC_LENGTH` and `MAX_LAYERS`; deployments with a larger topic
+/// space can raise them and validate with `Topic::new_with` / `TopicFilter::new_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopicLimits {
+    pub max_length: usize,
+    pub max_layers: usize,
+}
+
+impl Default for TopicLimits {
+    fn default() -> Self {
+        Self { max_length: MAX_TOPIC_LENGTH, max_layers: MAX_LAYERS }
+    }
+}
+
 /// A validated publish topic. Wildcards are not allowed.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Topic(Bytes);
 
 impl Topic {
     pub fn new(bytes: BytesMut) -> Result<Self, TopicError> {
+        Self::new_with(bytes, &TopicLimits::default())
+    }
+
+    pub fn new_with(bytes: BytesMut, limits: &TopicLimits) -> Result<Self, TopicError> {
         let bytes = bytes.freeze();
-        validate_segments(&bytes).and_then(|s| validate_no_wildcards(&s))?;
+        validate_with(&bytes, limits, validate_no_wildcards)?;
         Ok(Topic(bytes))
     }
 
@@ -39,6 +60,16 @@ impl Topic {
         &self.0
     }
 
+    /// Shares the topic's buffer, e.g. to fill a frame field without copying.
+    pub fn to_bytes(&self) -> Bytes {
+        self.0.clone()
+    }
+
+    /// The topic as text. Validation guarantees UTF-8.
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("topic validated as UTF-8")
+    }
+
     pub fn segments(&self) -> impl Iterator<Item = &[u8]> + '_ {
         self.0.split(|&byte| byte == SEP_BYTE).filter(|s| !s.is_empty())
     }
@@ -53,15 +84,42 @@ impl fmt::Display for Topic {
     }
 }
 
-impl From<Bytes> for Topic {
-    fn from(bytes: Bytes) -> Self {
-        Topic(bytes)
+impl AsRef<str> for Topic {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Compares against configured topic strings without allocating.
+impl PartialEq<str> for Topic {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl FromStr for Topic {
+    type Err = TopicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Topic::new(BytesMut::from(s))
     }
 }
 
-impl From<&'static [u8]> for Topic {
-    fn from(bytes: &'static [u8]) -> Self {
-        Topic(Bytes::from_static(bytes))
+impl TryFrom<&str> for Topic {
+    type Error = TopicError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Validates a received buffer in place, without copying it.
+impl TryFrom<Bytes> for Topic {
+    type Error = TopicError;
+
+    fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+        validate_with(&bytes, &TopicLimits::default(), validate_no_wildcards)?;
+        Ok(Topic(bytes))
     }
 }
 
@@ -71,8 +129,12 @@ pub struct TopicFilter(Bytes);
 
 impl TopicFilter {
     pub fn new(bytes: BytesMut) -> Result<Self, TopicError> {
+        Self::new_with(bytes, &TopicLimits::default())
+    }
+
+    pub fn new_with(bytes: BytesMut, limits: &TopicLimits) -> Result<Self, TopicError> {
         let bytes = bytes.freeze();
-        validate_segments(&bytes).and_then(|s| validate_wildcard_placement(&s))?;
+        validate_with(&bytes, limits, validate_wildcard_placement)?;
         Ok(TopicFilter(bytes))
     }
 
@@ -80,9 +142,85 @@ impl TopicFilter {
         &self.0
     }
 
+    /// The topic as text. Validation guarantees UTF-8.
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.0).expect("topic validated as UTF-8")
+    }
+
     pub fn segments(&self) -> impl Iterator<Item = &[u8]> + '_ {
         self.0.split(|&byte| byte == SEP_BYTE).filter(|s| !s.is_empty())
     }
+
+    /// True when the filter has no wildcard layers and so matches a single topic.
+    pub fn is_exact(&self) -> bool {
+        !has_wildcard(&self.0)
+    }
+
+    /// The single topic an exact filter matches, sharing the filter's buffer.
+    pub fn to_topic(&self) -> Option<Topic> {
+        self.is_exact().then(|| Topic(self.0.clone()))
+    }
+
+    /// Returns true when `topic` is matched by this filter.
+    /// `+` matches exactly one layer and a terminal `#` matches the remaining zero or more
+    /// layers; every other layer must be equal.
+    pub fn matches(&self, topic: &Topic) -> bool {
+        // Filters without wildcards can only match the identical topic.
+        if self.is_exact() {
+            return self.0 == topic.0;
+        }
+
+        let mut topic_layers = topic.segments();
+        for filter_layer in self.segments() {
+            if filter_layer == WILDCARD_MULTI {
+                return true;
+            }
+            match topic_layers.next() {
+                Some(layer) if filter_layer == WILDCARD_SINGLE || filter_layer == layer => {}
+                _ => return false,
+            }
+        }
+        topic_layers.next().is_none()
+    }
+}
+
+impl AsRef<str> for TopicFilter {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Compares against configured topic strings without allocating.
+impl PartialEq<str> for TopicFilter {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl FromStr for TopicFilter {
+    type Err = TopicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TopicFilter::new(BytesMut::from(s))
+    }
+}
+
+impl TryFrom<&str> for TopicFilter {
+    type Error = TopicError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Validates a received buffer in place, without copying it.
+impl TryFrom<Bytes> for TopicFilter {
+    type Error = TopicError;
+
+    fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+        validate_with(&bytes, &TopicLimits::default(), validate_wildcard_placement)?;
+        Ok(TopicFilter(bytes))
+    }
 }
 
 impl fmt::Display for TopicFilter {
@@ -94,13 +232,20 @@ impl fmt::Display for TopicFilter {
     }
 }
 
-fn validate_raw(raw: &[u8]) -> Result<&[u8], TopicError> {
+fn validate_raw<'a>(raw: &'a [u8], limits: &TopicLimits) -> Result<&'a [u8], TopicError> {
     if raw.is_empty() {
         return Err(TopicError::Empty);
     }
-    if raw.len() > MAX_TOPIC_LENGTH {
+    if raw.len() > limits.max_length {
         return Err(TopicError::TooLong { len: raw.len() });
     }
+    // Checked before any slash scanning so malformed bytes are rejected early.
+    if std::str::from_utf8(raw).is_err() {
+        return Err(TopicError::InvalidUtf8);
+    }
+    if raw.contains(&NUL_BYTE) {
+        return Err(TopicError::ContainsNul);
+    }
     if raw[0] == SEP_BYTE {
         return Err(TopicError::LeadingSlash);
     }
@@ -110,14 +255,30 @@ fn validate_raw(raw: &[u8]) -> Result<&[u8], TopicError> {
     Ok(raw)
 }
 
-fn validate_segments(raw: &[u8]) -> Result<Vec<&[u8]>, TopicError> {
-    let raw = validate_raw(raw)?;
+/// Validates `raw` and its layers, then applies the publish- or subscribe-specific `check`.
+/// Single-layer topics, the common case, skip the split and the layer vector allocation.
+fn validate_with(
+    raw: &[u8],
+    limits: &TopicLimits,
+    check: fn(&[&[u8]]) -> Result<(), TopicError>,
+) -> Result<(), TopicError> {
+    let raw = validate_raw(raw, limits)?;
+    if !raw.contains(&SEP_BYTE) {
+        let segments = [raw];
+        validate_segments(&segments, limits)?;
+        return check(&segments);
+    }
+
     let segments: Vec<&[u8]> = raw.split(|&byte| byte == SEP_BYTE).collect();
+    validate_segments(&segments, limits)?;
+    check(&segments)
+}
 
+fn validate_segments(segments: &[&[u8]], limits: &TopicLimits) -> Result<(), TopicError> {
     if segments.iter().any(|s| s.is_empty()) {
         return Err(TopicError::EmptyLayer);
     }
-    if segments.len() > MAX_LAYERS {
+    if segments.len() > limits.max_layers {
         return Err(TopicError::TooManyLayers { count: segments.len() });
     }
     if segments[0] == SYS_PREFIX {
@@ -126,7 +287,7 @@ fn validate_segments(raw: &[u8]) -> Result<Vec<&[u8]>, TopicError> {
     if segments[0] == GLOBAL_PREFIX && segments.len() < 2 {
         return Err(TopicError::GlobalPrefixWithoutTopic);
     }
-    Ok(segments)
+    Ok(())
 }
 
 fn has_wildcard(seg: &[u8]) -> bool {
@@ -143,8 +304,10 @@ fn validate_no_wildcards(segments: &[&[u8]]) -> Result<(), TopicError> {
     })
 }
 
+/// A wildcard must be the whole layer: `a+`, `+#` and `#+` are all rejected.
 fn validate_wildcard_placement(segments: &[&[u8]]) -> Result<(), TopicError> {
     let matchable = matchable_segments(segments);
+    let prefix_layers = segments.len() - matchable.len();
 
     matchable.iter().enumerate().try_for_each(|(i, seg)| {
         if *seg == WILDCARD_SINGLE || *seg == WILDCARD_MULTI {
@@ -153,7 +316,7 @@ fn validate_wildcard_placement(segments: &[&[u8]]) -> Result<(), TopicError> {
             }
             Ok(())
         } else if has_wildcard(seg) {
-            Err(TopicError::InvalidWildcardUsage)
+            Err(TopicError::InvalidWildcardLayer { layer_index: prefix_layers + i })
         } else {
             Ok(())
         }
@@ -182,6 +345,39 @@ mod tests {
         TopicFilter::new(BytesMut::from(s))
     }
 
+    #[test]
+    fn topic_compares_equal_to_str() {
+        assert!(topic("a/b") == *"a/b");
+    }
+
+    #[test]
+    fn filter_as_ref_is_the_filter_text() {
+        let filter = filter("a/+");
+        let text: &str = filter.as_ref();
+        assert_eq!(text, "a/+");
+    }
+
+    #[test]
+    fn topic_parses_from_str() {
+        assert_eq!("a/b".parse::<Topic>(), Ok(topic("a/b")));
+    }
+
+    #[test]
+    fn topic_from_str_rejects_wildcard() {
+        assert!(matches!(Topic::try_from("a/+"), Err(TopicError::WildcardInPublishTopic)));
+    }
+
+    #[test]
+    fn filter_parses_from_str() {
+        assert_eq!("a/#".parse::<TopicFilter>(), Ok(filter("a/#")));
+    }
+
+    #[test]
+    fn filter_try_from_bytes_runs_validation() {
+        let result = TopicFilter::try_from(Bytes::from_static(b"a/#/b"));
+        assert!(matches!(result, Err(TopicError::MultiWildcardNotTerminal)));
+    }
+
     #[test]
     fn segments_splits_simple_path() {
         let t = topic("a/b/c");
@@ -196,6 +392,22 @@ mod tests {
         assert_eq!(segs, vec![b"single".as_ref()]);
     }
 
+    #[test]
+    fn segments_single_layer_returns_whole_topic() {
+        let t = topic("temperature");
+        assert_eq!(t.segments().next(), Some(t.as_bytes()));
+    }
+
+    #[test]
+    fn parse_rejects_single_layer_sys_prefix() {
+        assert_eq!(parse_pub("$SYS"), Err(TopicError::ReservedSysPrefix));
+    }
+
+    #[test]
+    fn parse_rejects_single_layer_embedded_wildcard() {
+        assert_eq!(parse_sub("sensor+"), Err(TopicError::InvalidWildcardLayer { layer_index: 0 }));
+    }
+
     #[test]
     fn parse_rejects_empty_topic() {
         assert_eq!(parse_pub(""), Err(TopicError::Empty));
@@ -238,6 +450,20 @@ mod tests {
         assert!(parse_pub("a/b/c/d/e/f/g/h").is_ok());
     }
 
+    #[test]
+    fn raised_layer_limit_accepts_9_layers() {
+        let limits = TopicLimits { max_layers: MAX_LAYERS + 1, ..TopicLimits::default() };
+
+        assert!(Topic::new_with(BytesMut::from("a/b/c/d/e/f/g/h/i"), &limits).is_ok());
+    }
+
+    #[test]
+    fn raised_layer_limit_applies_to_filters() {
+        let limits = TopicLimits { max_layers: MAX_LAYERS + 1, ..TopicLimits::default() };
+
+        assert!(TopicFilter::new_with(BytesMut::from("a/b/c/d/e/f/g/h/#"), &limits).is_ok());
+    }
+
     #[test]
     fn parse_rejects_sys_prefix() {
         assert_eq!(parse_pub("$SYS/status"), Err(TopicError::ReservedSysPrefix));
@@ -320,17 +546,112 @@ mod tests {
 
     #[test]
     fn parse_rejects_embedded_wildcard_plus() {
-        assert_eq!(parse_sub("sensor+/data"), Err(TopicError::InvalidWildcardUsage));
+        assert_eq!(
+            parse_sub("sensor+/data"),
+            Err(TopicError::InvalidWildcardLayer { layer_index: 0 })
+        );
     }
 
     #[test]
     fn parse_rejects_embedded_wildcard_hash() {
-        assert_eq!(parse_sub("sensor#/data"), Err(TopicError::InvalidWildcardUsage));
+        assert_eq!(
+            parse_sub("sensor#/data"),
+            Err(TopicError::InvalidWildcardLayer { layer_index: 0 })
+        );
     }
 
     #[test]
     fn parse_rejects_embedded_wildcard_plus_suffix() {
-        assert_eq!(parse_sub("sensor/data+"), Err(TopicError::InvalidWildcardUsage));
+        assert_eq!(
+            parse_sub("sensor/data+"),
+            Err(TopicError::InvalidWildcardLayer { layer_index: 1 })
+        );
+    }
+
+    #[test]
+    fn parse_rejects_single_wildcard_followed_by_multi_wildcard() {
+        assert_eq!(parse_sub("a/+#"), Err(TopicError::InvalidWildcardLayer { layer_index: 1 }));
+    }
+
+    #[test]
+    fn parse_rejects_multi_wildcard_followed_by_single_wildcard() {
+        assert_eq!(parse_sub("#+"), Err(TopicError::InvalidWildcardLayer { layer_index: 0 }));
+    }
+
+    #[test]
+    fn parse_rejects_wildcard_mixed_with_text() {
+        assert_eq!(parse_sub("a+"), Err(TopicError::InvalidWildcardLayer { layer_index: 0 }));
+    }
+
+    #[test]
+    fn parse_reports_layer_index_after_global_prefix() {
+        assert_eq!(parse_sub("$G/a/b+"), Err(TopicError::InvalidWildcardLayer { layer_index: 2 }));
+    }
+
+    #[test]
+    fn filter_matches_topics() {
+        let cases = [
+            // Exact filters.
+            ("sensor/temp", "sensor/temp", true),
+            ("sensor/temp", "sensor/humidity", false),
+            ("sensor/temp", "sensor/temp/max", false),
+            // Single-layer wildcard.
+            ("sensor/+", "sensor/temp", true),
+            ("+/+", "a/b", true),
+            ("+/+", "a/b/c", false),
+            ("+/temp", "sensor", false),
+            // Multi-layer wildcard.
+            ("sensor/#", "sensor", true),
+            ("sensor/#", "sensor/a/b", true),
+            ("sensor/#", "actuator/a", false),
+            ("#", "any/topic/at/all", true),
+            // Both wildcards.
+            ("+/data/#", "sensor/data", true),
+            ("+/data/#", "sensor/state/a", false),
+        ];
+
+        for (filter_str, topic_str, expected) in cases {
+            assert_eq!(
+                filter(filter_str).matches(&topic(topic_str)),
+                expected,
+                "filter {filter_str} against topic {topic_str}"
+            );
+        }
+    }
+
+    #[test]
+    fn topics_with_same_bytes_are_one_hash_set_entry() {
+        let topics: std::collections::HashSet<Topic> =
+            [topic("sensor/temp"), topic("sensor/temp")].into_iter().collect();
+        assert_eq!(topics.len(), 1);
+    }
+
+    #[test]
+    fn parse_accepts_multibyte_utf8_topic() {
+        assert_eq!(topic("温度/値").as_str(), "温度/値");
+    }
+
+    #[test]
+    fn parse_rejects_invalid_utf8() {
+        assert_eq!(Topic::new(BytesMut::from(&b"sensor/\xFF"[..])), Err(TopicError::InvalidUtf8));
+    }
+
+    #[test]
+    fn try_from_bytes_rejects_invalid_utf8() {
+        assert_eq!(
+            Topic::try_from(Bytes::from_static(b"sensor/\xFF")),
+            Err(TopicError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn wildcard_filter_has_no_single_topic() {
+        assert_eq!(filter("sensor/+").to_topic(), None);
+    }
+
+    #[test]
+    fn parse_rejects_embedded_nul() {
+        assert_eq!(parse_pub("sensor/\0/temp"), Err(TopicError::ContainsNul));
     }
 
     #[test]
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+
+pub use crate::error::TopicAliasError;
+use crate::parser::pb;
+
+/// Per-connection table of topic aliases set by the client's publishes.
+/// A Publish carrying both a topic and an alias (re)binds the alias; a later Publish with the
+/// alias and an empty topic is expanded back to the bound topic.
+pub struct TopicAliasTable {
+    maximum: u32,
+    topics: HashMap<u32, Bytes>,
+}
+
+impl TopicAliasTable {
+    /// `maximum` is the highest alias the client may use; 0 disables aliasing.
+    pub fn new(maximum: u32) -> Self {
+        Self { maximum, topics: HashMap::new() }
+    }
+
+    /// Binds or expands the alias on `publish`, leaving its topic filled in.
+    /// Publishes without an alias pass through untouched.
+    pub fn resolve(&mut self, publish: &mut pb::Publish) -> Result<(), TopicAliasError> {
+        let Some(alias) = publish.topic_alias else { return Ok(()) };
+        if alias == 0 || alias > self.maximum {
+            return Err(TopicAliasError::OutOfRange { alias, maximum: self.maximum });
+        }
+        if publish.topic.is_empty() {
+            let topic = self.topics.get(&alias).ok_or(TopicAliasError::Unbound { alias })?;
+            publish.topic = topic.clone();
+        } else {
+            self.topics.insert(alias, publish.topic.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALIAS_MAXIMUM: u32 = 4;
+
+    fn publish(topic: &[u8], topic_alias: Option<u32>) -> pb::Publish {
+        pb::Publish { topic: Bytes::copy_from_slice(topic), topic_alias, ..Default::default() }
+    }
+
+    #[test]
+    fn alias_only_publish_expands_to_bound_topic() {
+        let mut table = TopicAliasTable::new(ALIAS_MAXIMUM);
+        table.resolve(&mut publish(b"sensors/temperature", Some(1))).unwrap();
+        let mut alias_only = publish(b"", Some(1));
+
+        table.resolve(&mut alias_only).unwrap();
+
+        assert_eq!(alias_only.topic, &b"sensors/temperature"[..]);
+    }
+
+    #[test]
+    fn unbound_alias_is_rejected() {
+        let mut table = TopicAliasTable::new(ALIAS_MAXIMUM);
+
+        let result = table.resolve(&mut publish(b"", Some(2)));
+
+        assert_eq!(result, Err(TopicAliasError::Unbound { alias: 2 }));
+    }
+
+    #[test]
+    fn alias_above_maximum_is_rejected() {
+        let mut table = TopicAliasTable::new(ALIAS_MAXIMUM);
+
+        let result = table.resolve(&mut publish(b"a/b", Some(ALIAS_MAXIMUM + 1)));
+
+        assert!(matches!(result, Err(TopicAliasError::OutOfRange { .. })));
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, SyncSender, TrySendError},
+    },
+    thread::{self, JoinHandle},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    clock::Clock,
+    parser::{CommandCodec, Frame, encode_frame, pb},
+};
+
+const TIMESTAMP_BYTES: usize = 8;
+const DIRECTION_BYTES: usize = 1;
+const FRAME_LENGTH_BYTES: usize = 4;
+const RECORD_HEADER_LENGTH: usize = TIMESTAMP_BYTES + DIRECTION_BYTES + FRAME_LENGTH_BYTES;
+
+/// Records queued for the writer thread; further records are dropped while it is full so
+/// tracing never stalls a connection.
+const TRACE_QUEUE_CAPACITY: usize = 4096;
+
+/// Which way a traced frame travelled, from the server's point of view.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    Inbound = 0,
+    Outbound = 1,
+}
+
+impl TryFrom<u8> for TraceDirection {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TraceDirection::Inbound),
+            1 => Ok(TraceDirection::Outbound),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unknown trace direction")),
+        }
+    }
+}
+
+/// One traced frame: `timestamp_ms (u64) | direction (u8) | frame length (u32) | frame`.
+/// `frame` is the complete wire frame, so it can be fed straight back into a codec.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub timestamp_ms: u64,
+    pub direction: TraceDirection,
+    pub frame: Bytes,
+}
+
+/// Appends every frame crossing a client connection to a trace file for offline inspection.
+/// Records are written by a dedicated thread, so callers on async tasks never block on disk
+/// I/O. Dropping the tracer writes out the queued records before returning.
+pub struct FrameTracer {
+    records: Option<SyncSender<BytesMut>>,
+    writer_thread: Mutex<Option<JoinHandle<()>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl FrameTracer {
+    pub fn new(mut writer: Box<dyn Write + Send>, clock: Arc<dyn Clock>) -> Self {
+        let (records, queued) = mpsc::sync_channel::<BytesMut>(TRACE_QUEUE_CAPACITY);
+        let writer_thread = thread::spawn(move || {
+            for record in queued {
+                if let Err(error) = writer.write_all(&record) {
+                    tracing::warn!("frame trace stopped: {error}");
+                    return;
+                }
+            }
+            let _ = writer.flush();
+        });
+        Self { records: Some(records), writer_thread: Mutex::new(Some(writer_thread)), clock }
+    }
+
+    /// Creates (or truncates) the trace file at `path`.
+    pub fn create(path: &str, clock: Arc<dyn Clock>) -> io::Result<Self> {
+        Ok(Self::new(Box::new(File::create(path)?), clock))
+    }
+
+    pub fn record<T: CommandCodec>(&self, direction: TraceDirection, item: &T) -> io::Result<()> {
+        let mut frame = BytesMut::new();
+        encode_frame(item, &mut frame).map_err(io::Error::other)?;
+        let frame_length: u32 = frame
+            .len()
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large to trace"))?;
+
+        let mut record = BytesMut::with_capacity(RECORD_HEADER_LENGTH + frame.len());
+        record.put_u64(self.clock.now_ms());
+        record.put_u8(direction as u8);
+        record.put_u32(frame_length);
+        record.extend_from_slice(&frame);
+
+        // Whole records are queued, so records stay intact when several clients trace at once.
+        let Some(records) = &self.records else { return Ok(()) };
+        records.try_send(record).map_err(|error| match error {
+            TrySendError::Full(_) => io::Error::new(io::ErrorKind::WouldBlock, "trace queue full"),
+            TrySendError::Disconnected(_) => io::Error::other("trace writer stopped"),
+        })
+    }
+
+    /// Records a frame received from a client. CONNECT credentials are removed first so
+    /// passwords and tokens never reach the trace file.
+    pub fn record_inbound(&self, frame: &Frame) -> io::Result<()> {
+        match frame {
+            Frame::Connect(connect) => {
+                let connect = pb::Connect { credentials: None, ..connect.clone() };
+                self.record(TraceDirection::Inbound, &connect)
+            }
+            Frame::Publish(publish) => self.record(TraceDirection::Inbound, publish),
+            Frame::Subscribe(subscribe) => self.record(TraceDirection::Inbound, subscribe),
+            Frame::UnSubscribe(unsubscribe) => self.record(TraceDirection::Inbound, unsubscribe),
+            Frame::Flow(flow) => self.record(TraceDirection::Inbound, flow),
+            Frame::MessageAck(ack) => self.record(TraceDirection::Inbound, ack),
+            Frame::Ping(ping) => self.record(TraceDirection::Inbound, ping),
+            Frame::Disconnect(disconnect) => self.record(TraceDirection::Inbound, disconnect),
+            Frame::Extension(extension) => self.record(TraceDirection::Inbound, extension),
+        }
+    }
+}
+
+impl Drop for FrameTracer {
+    fn drop(&mut self) {
+        // Closing the queue ends the writer thread once it has written what is left.
+        drop(self.records.take());
+        let writer_thread = self.writer_thread.get_mut().map(Option::take);
+        if let Ok(Some(writer_thread)) = writer_thread {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Reads the next record from a trace file. Returns `Ok(None)` at a clean end of file, i.e.
+/// when no byte of a further record exists; a record cut short is an `UnexpectedEof` error.
+#[allow(dead_code)]
+pub fn read_record(reader: &mut impl Read) -> io::Result<Option<TraceRecord>> {
+    let mut header = [0u8; RECORD_HEADER_LENGTH];
+    let mut filled = 0;
+    while filled < RECORD_HEADER_LENGTH {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "trace ends inside a record header",
+                ));
+            }
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    let (timestamp_bytes, rest) = header.split_at(TIMESTAMP_BYTES);
+    let (direction_byte, frame_length_bytes) = rest.split_at(DIRECTION_BYTES);
+    let timestamp_ms = u64::from_be_bytes(timestamp_bytes.try_into().expect("fixed-size slice"));
+    let direction = TraceDirection::try_from(direction_byte[0])?;
+    let frame_length =
+        u32::from_be_bytes(frame_length_bytes.try_into().expect("fixed-size slice")) as usize;
+
+    let mut frame = vec![0u8; frame_length];
+    reader.read_exact(&mut frame)?;
+    Ok(Some(TraceRecord { timestamp_ms, direction, frame: Bytes::from(frame) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tokio_util::codec::Decoder;
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        parser::{ClientCodec, ClientFrame, ClientOutbound, ServerCodec, pb},
+    };
+
+    /// Shares the traced bytes with the test after the tracer takes ownership of the writer.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn traced_frames_read_back_into_decoded_messages() {
+        let buffer = SharedBuffer::default();
+        let tracer = FrameTracer::new(Box::new(buffer.clone()), Arc::new(MockClock::new(42)));
+        let connect = ClientOutbound::connect(1, false);
+        let message = pb::Message { subscription_id: 3, ..Default::default() };
+
+        tracer.record_inbound(&Frame::Connect(connect.clone())).unwrap();
+        tracer.record(TraceDirection::Outbound, &message).unwrap();
+        drop(tracer);
+
+        let traced = buffer.0.lock().unwrap().clone();
+        let mut reader = Cursor::new(traced);
+        let inbound = read_record(&mut reader).unwrap().unwrap();
+        let outbound = read_record(&mut reader).unwrap().unwrap();
+        assert!(read_record(&mut reader).unwrap().is_none());
+
+        let decoded_inbound =
+            ServerCodec::default().decode(&mut BytesMut::from(&inbound.frame[..])).unwrap();
+        let decoded_outbound =
+            ClientCodec::default().decode(&mut BytesMut::from(&outbound.frame[..])).unwrap();
+        assert_eq!(decoded_inbound, Some(Frame::Connect(connect)));
+        assert_eq!(decoded_outbound, Some(ClientFrame::Message(message)));
+    }
+
+    #[test]
+    fn traced_connect_has_no_credentials() {
+        let buffer = SharedBuffer::default();
+        let tracer = FrameTracer::new(Box::new(buffer.clone()), Arc::new(MockClock::new(42)));
+        let connect = ClientOutbound::connect_with_token(1, false, b"secret-token".to_vec());
+
+        tracer.record_inbound(&Frame::Connect(connect)).unwrap();
+        drop(tracer);
+
+        let traced = buffer.0.lock().unwrap().clone();
+        let record = read_record(&mut Cursor::new(traced)).unwrap().unwrap();
+        let decoded = ServerCodec::default().decode(&mut BytesMut::from(&record.frame[..]));
+        let Ok(Some(Frame::Connect(connect))) = decoded else { panic!("expected Connect") };
+        assert_eq!(connect.credentials, None);
+    }
+
+    #[test]
+    fn truncated_record_header_is_an_error() {
+        let mut reader = Cursor::new(vec![0u8; RECORD_HEADER_LENGTH - 1]);
+
+        let error = read_record(&mut reader).unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,10 +1,12 @@
 use std::{net::UdpSocket, path::Path, sync::Arc, time::Duration};
 
 use bytes::BytesMut;
-use s2n_quic::{Client, client::Connect};
+use s2n_quic::{Client, client::Connect, connection};
 use server::{
+    auth::{AuthOutcome, Authenticator, NoAuthAuthenticator},
     config::ServerConfig,
-    error::ClientCodecError,
+    error::{ClientCodecError, ErrorCode},
+    interceptor::{Action, Interceptor, InterceptorChain},
     parser::{ClientCodec, ClientFrame, ClientOutbound, CommandCodec, ServerOutbound, pb},
 };
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
@@ -36,7 +38,7 @@ async fn read_next_client_frame<ReceiveStream>(
 where
     ReceiveStream: AsyncRead + Unpin,
 {
-    let mut client_codec = ClientCodec;
+    let mut client_codec = ClientCodec::default();
     loop {
         if let Some(frame) = client_codec.decode(incoming_bytes)? {
             return Ok(Some(frame));
@@ -57,16 +59,54 @@ where
     SendStream: AsyncWrite + Unpin,
     Message: CommandCodec,
 {
-    let mut client_codec = ClientCodec;
+    let mut client_codec = ClientCodec::default();
     let mut output_buffer = BytesMut::new();
     client_codec.encode(message, &mut output_buffer)?;
     send_stream.write_all(&output_buffer).await?;
     Ok(())
 }
 
+struct RejectAllAuthenticator;
+
+impl Authenticator for RejectAllAuthenticator {
+    fn authenticate(&self, _connect: &pb::Connect, _nonce: &[u8]) -> AuthOutcome {
+        AuthOutcome::Rejected { reason: "rejected by test".to_string() }
+    }
+}
+
+/// Waits for the server to close the connection and returns the application error code it sent.
+async fn application_close_code(connection: &mut s2n_quic::Connection) -> Option<u64> {
+    match connection.accept_bidirectional_stream().await {
+        Err(connection::Error::Application { error, .. }) => Some(error.into()),
+        _ => None,
+    }
+}
+
 async fn setup_server_and_client(
     connect_timeout: u64,
 ) -> Result<(Arc<ServerConfig>, CancellationToken, s2n_quic::Client, std::net::SocketAddr), TestError>
+{
+    setup_server_and_client_with_authenticator(connect_timeout, Arc::new(NoAuthAuthenticator)).await
+}
+
+async fn setup_server_and_client_with_authenticator(
+    connect_timeout: u64,
+    authenticator: Arc<dyn Authenticator>,
+) -> Result<(Arc<ServerConfig>, CancellationToken, s2n_quic::Client, std::net::SocketAddr), TestError>
+{
+    setup_server_and_client_with_interceptors(
+        connect_timeout,
+        authenticator,
+        InterceptorChain::new(),
+    )
+    .await
+}
+
+async fn setup_server_and_client_with_interceptors(
+    connect_timeout: u64,
+    authenticator: Arc<dyn Authenticator>,
+    interceptors: InterceptorChain,
+) -> Result<(Arc<ServerConfig>, CancellationToken, s2n_quic::Client, std::net::SocketAddr), TestError>
 {
     let mut server_config = ServerConfig::new();
     server_config.quic.enable_gso = false;
@@ -80,7 +120,13 @@ async fn setup_server_and_client(
     let server_config = Arc::new(server_config);
     let server_shutdown = cancellation_token.clone();
 
-    let server_address = server::quic::start(Arc::clone(&server_config), server_shutdown).await?;
+    let server_address = server::quic::start_with_interceptors(
+        Arc::clone(&server_config),
+        authenticator,
+        interceptors,
+        server_shutdown,
+    )
+    .await?;
 
     let client = Client::builder()
         .with_tls(Path::new("../certs/server.crt"))?
@@ -177,12 +223,88 @@ async fn connect_timeout_when_no_connect_message() -> Result<(), TestError> {
             }
         };
 
-    // Don't send CONNECT message - just wait for the stream to be closed due to timeout
-    tokio::time::sleep(Duration::from_secs(2)).await;
+    // Don't send CONNECT message - the server should close the connection due to timeout.
+    let close_code = application_close_code(&mut connection).await;
+    assert_eq!(close_code, Some(ErrorCode::ConnectTimeout as u64));
+
+    cancellation_token.cancel();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn auth_failure_closes_connection_with_auth_failed_code() -> Result<(), TestError> {
+    let (_server_config, cancellation_token, client, server_address) =
+        setup_server_and_client_with_authenticator(1000, Arc::new(RejectAllAuthenticator)).await?;
+
+    let connect = Connect::new(server_address).with_server_name("localhost");
+    let mut connection = client.connect(connect).await?;
+    connection.keep_alive(true)?;
+
+    let stream = connection.open_bidirectional_stream().await?;
+    let (mut receive_stream, mut send_stream) = stream.split();
+
+    let mut incoming_bytes = BytesMut::new();
+    let info_frame = read_next_client_frame(&mut receive_stream, &mut incoming_bytes).await?;
+    assert!(matches!(info_frame, Some(ClientFrame::Info(_))));
+
+    write_client_frame(&mut send_stream, sample_connect_message()).await?;
+
+    let close_code = application_close_code(&mut connection).await;
+    assert_eq!(close_code, Some(ErrorCode::AuthFailed as u64));
+
+    cancellation_token.cancel();
+
+    Ok(())
+}
+
+struct DropAllPublishes;
+
+impl Interceptor for DropAllPublishes {
+    fn on_publish(&self, _publish: &mut pb::Publish) -> Action {
+        Action::Drop
+    }
+}
+
+#[tokio::test]
+async fn interceptors_passed_at_start_see_publishes() -> Result<(), TestError> {
+    const PUBLISH_ID: u64 = 9;
+    let mut interceptors = InterceptorChain::new();
+    interceptors.register(Arc::new(DropAllPublishes));
+    let (_server_config, cancellation_token, client, server_address) =
+        setup_server_and_client_with_interceptors(
+            1000,
+            Arc::new(NoAuthAuthenticator),
+            interceptors,
+        )
+        .await?;
+
+    let connect = Connect::new(server_address).with_server_name("localhost");
+    let mut connection = client.connect(connect).await?;
+    connection.keep_alive(true)?;
+
+    let stream = connection.open_bidirectional_stream().await?;
+    let (mut receive_stream, mut send_stream) = stream.split();
+
+    let mut incoming_bytes = BytesMut::new();
+    let info_frame = read_next_client_frame(&mut receive_stream, &mut incoming_bytes).await?;
+    assert!(matches!(info_frame, Some(ClientFrame::Info(_))));
+
+    write_client_frame(&mut send_stream, sample_connect_message()).await?;
+    let publish = pb::Publish {
+        topic: bytes::Bytes::from_static(b"a/b"),
+        publish_id: PUBLISH_ID,
+        ..Default::default()
+    };
+    write_client_frame(&mut send_stream, publish).await?;
 
-    // The server should have closed the connection due to timeout
-    let bytes_read = receive_stream.read_buf(&mut incoming_bytes).await?;
-    assert_eq!(bytes_read, 0, "Expected stream to be closed by server due to timeout");
+    let frame = read_next_client_frame(&mut receive_stream, &mut incoming_bytes).await?;
+    assert!(matches!(
+        frame,
+        Some(ClientFrame::Error(error))
+            if error.publish_id == PUBLISH_ID
+                && error.code == ErrorCode::PermissionViolation as u32
+    ));
 
     cancellation_token.cancel();
 